@@ -50,6 +50,15 @@ struct ProjectSearch {
     query: Option<String>,
 }
 
+#[derive(StructOpt, Debug)]
+struct Call {
+    /// Method name, e.g. maniphest.search
+    method: String,
+    /// Parameters as a JSON object
+    #[structopt(default_value = "{}")]
+    params: String,
+}
+
 #[derive(StructOpt, Debug)]
 enum Command {
     #[structopt(name = "phid.lookup")]
@@ -62,13 +71,20 @@ enum Command {
     ManiphestSearch(ManiphestSearch),
     #[structopt(name = "project.search")]
     ProjectSearch(ProjectSearch),
+    /// List the methods of the server
+    #[structopt(name = "conduit.query")]
+    ConduitQuery,
+    /// Call any method
+    Call(Call),
 }
 
 #[derive(StructOpt)]
 struct Opt {
-    #[structopt(short, long, default_value = "https://phabricator.collabora.com/")]
-    server: String,
-    token: String,
+    /// Server url, defaults to the arc configuration
+    #[structopt(short, long)]
+    server: Option<String>,
+    /// API token, defaults to the arc configuration
+    token: Option<String>,
     #[structopt(subcommand)]
     command: Command,
 }
@@ -148,15 +164,42 @@ async fn project_search(client: Client, p: ProjectSearch) -> Result<()> {
     Ok(())
 }
 
+async fn conduit_query(client: Client) -> Result<()> {
+    let r = client.request(&conduit::Query {}).await?;
+    let mut methods: Vec<_> = r.0.into_iter().collect();
+    methods.sort_by(|a, b| a.0.cmp(&b.0));
+    for (name, method) in methods {
+        let mut params: Vec<_> = method
+            .params
+            .iter()
+            .map(|(p, t)| format!("{}: {}", p, t))
+            .collect();
+        params.sort();
+        println!("{}({}) -> {}", name, params.join(", "), method.returns);
+    }
+    Ok(())
+}
+
+async fn call(client: Client, c: Call) -> Result<()> {
+    let params = serde_json::from_str(&c.params)?;
+    let r = client.call_raw(&c.method, params).await?;
+    println!("{}", serde_json::to_string_pretty(&r)?);
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
     let opts = Opt::from_args();
 
-    let client = Client::new(
-        opts.server.parse().expect("Failed to parse server url"),
-        opts.token,
-    );
+    let mut loader = config::Config::loader();
+    if let Some(server) = opts.server {
+        loader = loader.url(server);
+    }
+    if let Some(token) = opts.token {
+        loader = loader.token(token);
+    }
+    let client = loader.load()?.client();
 
     match opts.command {
         Command::PhidLookup(l) => phid_lookup(client, l).await,
@@ -164,5 +207,7 @@ async fn main() -> Result<()> {
         Command::ManiphestInfo(i) => maniphest_info(client, i).await,
         Command::ManiphestSearch(i) => maniphest_search(client, i).await,
         Command::ProjectSearch(i) => project_search(client, i).await,
+        Command::ConduitQuery => conduit_query(client).await,
+        Command::Call(c) => call(client, c).await,
     }
 }
@@ -0,0 +1,94 @@
+//! Synchronous wrapper around the async [Client](crate::Client)
+//!
+//! The blocking client drives requests on its own single threaded runtime,
+//! so it mustn't be used from within an async context.
+use crate::{ApiRequest, RequestError};
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use url::Url;
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: crate::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl Client {
+    /// Client using the default HTTP transport
+    #[cfg(feature = "reqwest")]
+    pub fn new(base: Url, token: String) -> io::Result<Client> {
+        Self::try_from_async(crate::Client::new(base, token))
+    }
+
+    /// Wrap an async client, e.g. one set up with
+    /// [ClientBuilder](crate::ClientBuilder). Fails if the runtime can't be
+    /// started.
+    pub fn try_from_async(inner: crate::Client) -> io::Result<Client> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Client {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    pub fn base(&self) -> &Url {
+        self.inner.base()
+    }
+
+    /// The wrapped async client
+    pub fn as_async(&self) -> &crate::Client {
+        &self.inner
+    }
+
+    pub fn request<R>(&self, request: &R) -> Result<R::Reply, RequestError>
+    where
+        R: ApiRequest,
+    {
+        self.block_on(self.inner.request(request))
+    }
+
+    /// See [crate::Client::call_raw]
+    pub fn call_raw(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, RequestError> {
+        self.block_on(self.inner.call_raw(method, params))
+    }
+
+    /// Run a future to completion on the client's runtime, e.g. to use
+    /// async APIs built on top of the wrapped client
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use crate::phid::Lookup;
+    use phabricator_mock::PhabMockServer;
+
+    #[test]
+    fn request() {
+        // The mock server needs a runtime of its own to keep serving
+        let server = tokio::runtime::Runtime::new().unwrap();
+        let m = server.block_on(PhabMockServer::start());
+        let user = m.new_user("user", "Test User");
+        let task = m.new_simple_task(1, &user);
+
+        let client = Client::new(m.uri(), m.token().to_string()).unwrap();
+        let l = Lookup {
+            names: vec!["T1".to_string()],
+        };
+        let r = client.request(&l).unwrap();
+        assert_eq!(task.phid.to_string(), r.0["T1"].phid.0);
+
+        let client = Client::new(m.uri(), "wrong".to_string()).unwrap();
+        assert!(client.request(&l).unwrap_err().is_auth());
+    }
+}
@@ -1,18 +1,49 @@
+use super::error::RequestError;
+use super::middleware::{CompletedCall, Middleware, Middlewares, OutgoingCall};
+use super::retry::{RateLimit, RateLimiter, RetryPolicy};
 use super::ser::serialize_phab;
+#[cfg(feature = "reqwest")]
+use super::transport::HttpTransport;
+use super::transport::{Transport, TransportRequest};
+use http::header::HeaderMap;
+#[cfg(feature = "reqwest")]
+use http::header::{HeaderName, HeaderValue, USER_AGENT};
 use serde::Serialize;
 use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use std::sync::Arc;
+#[cfg(feature = "reqwest")]
+use std::time::Duration;
+use std::time::Instant;
 use thiserror::Error;
 use url::Url;
 
 pub trait ApiRequest: Serialize {
     type Reply: DeserializeOwned + std::fmt::Debug;
     const ROUTE: &'static str;
+    /// Whether the request only reads data, so it's safe to retry. Requests
+    /// are assumed to change data unless they override this.
+    const READ_ONLY: bool = false;
+    /// Encoding to always use for this request, e.g. when its parameters
+    /// don't fit the form encoding; `None` uses the client's encoding
+    const ENCODING: Option<Encoding> = None;
 
-    fn route(&self) -> &'static str {
+    fn route(&self) -> &str {
         Self::ROUTE
     }
 }
 
+/// How request parameters are sent to the server
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// Nested form fields, e.g. `constraints[ids][0]=1`
+    #[default]
+    Form,
+    /// A JSON document in the `params` field, with the token in
+    /// `__conduit__`. This handles floats and deeply nested values better.
+    Json,
+}
+
 #[derive(Debug, Serialize)]
 struct Request<'a, R: Serialize> {
     #[serde(rename = "api.token")]
@@ -21,6 +52,24 @@ struct Request<'a, R: Serialize> {
     request: &'a R,
 }
 
+// Call of a method by name, see [Client::call_raw]
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+struct RawCall {
+    #[serde(skip)]
+    route: String,
+    params: Value,
+}
+
+impl ApiRequest for RawCall {
+    type Reply = Value;
+    const ROUTE: &'static str = "api/";
+
+    fn route(&self) -> &str {
+        &self.route
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct Reply<R> {
     error_code: Option<String>,
@@ -32,52 +81,593 @@ struct Reply<R> {
 pub struct Client {
     base: Url,
     token: String,
-    client: reqwest::Client,
+    transport: Arc<dyn Transport>,
+    retry: Option<RetryPolicy>,
+    limiter: Option<Arc<RateLimiter>>,
+    middleware: Middlewares,
+    encoding: Encoding,
 }
 
 #[derive(Error, Debug)]
-pub enum RequestError {
-    #[error("API Error ({code}): {info}")]
-    Api { code: String, info: String },
-    #[error("Incomplete reply")]
-    Incomplete,
-    #[error("Request failure {0}")]
-    Request(#[from] reqwest::Error),
+pub enum BuildError {
+    #[cfg(feature = "reqwest")]
+    #[error("Failed to set up the HTTP client: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("No transport configured")]
+    NoTransport,
+}
+
+/// Builder for a [Client] with a customized transport
+#[derive(Debug)]
+pub struct ClientBuilder {
+    base: Url,
+    token: String,
+    transport: Option<Arc<dyn Transport>>,
+    #[cfg(feature = "reqwest")]
+    client: Option<reqwest::Client>,
+    #[cfg(feature = "reqwest")]
+    builder: reqwest::ClientBuilder,
+    #[cfg(feature = "reqwest")]
+    headers: HeaderMap,
+    retry: Option<RetryPolicy>,
+    rate_limit: Option<RateLimit>,
+    middleware: Middlewares,
+    encoding: Encoding,
+}
+
+impl ClientBuilder {
+    /// Send calls through a custom transport; the HTTP settings are ignored
+    /// then
+    pub fn transport<T: Transport + 'static>(mut self, transport: T) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Retry failed requests; by default they fail immediately
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Limit the request rate, shared by all clones of the client
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    /// Add a middleware; they run in the order they were added
+    pub fn middleware<M: Middleware + 'static>(mut self, middleware: M) -> Self {
+        self.middleware.0.push(Arc::new(middleware));
+        self
+    }
+
+    /// Encoding for requests that don't require a specific one
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    #[cfg(feature = "reqwest")]
+    fn http_transport(self) -> Result<Arc<dyn Transport>, BuildError> {
+        let client = match self.client {
+            Some(client) => client,
+            None => self.builder.default_headers(self.headers).build()?,
+        };
+        Ok(Arc::new(HttpTransport::new(client)))
+    }
+
+    pub fn build(mut self) -> Result<Client, BuildError> {
+        let base = self.base.clone();
+        let token = self.token.clone();
+        let retry = self.retry.take();
+        let limiter = self
+            .rate_limit
+            .take()
+            .map(|l| Arc::new(RateLimiter::new(l)));
+        let middleware = std::mem::take(&mut self.middleware);
+        let encoding = self.encoding;
+
+        let transport = match self.transport.take() {
+            Some(transport) => transport,
+            #[cfg(feature = "reqwest")]
+            None => self.http_transport()?,
+            #[cfg(not(feature = "reqwest"))]
+            None => return Err(BuildError::NoTransport),
+        };
+        Ok(Client {
+            base,
+            token,
+            transport,
+            retry,
+            limiter,
+            middleware,
+            encoding,
+        })
+    }
+}
+
+/// Settings for the default HTTP transport
+#[cfg(feature = "reqwest")]
+impl ClientBuilder {
+    /// Use a pre-configured HTTP client; the other settings are ignored then
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Timeout for a whole request, from connecting until the reply is read
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.builder = self.builder.timeout(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.builder = self.builder.connect_timeout(timeout);
+        self
+    }
+
+    /// Add a proxy; the system proxy settings are used unless one is added
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.builder = self.builder.proxy(proxy);
+        self
+    }
+
+    /// Ignore the system proxy settings
+    pub fn no_proxy(mut self) -> Self {
+        self.builder = self.builder.no_proxy();
+        self
+    }
+
+    /// Trust an additional root certificate, e.g. a private CA
+    #[cfg(any(
+        feature = "default-tls",
+        feature = "native-tls",
+        feature = "rustls-tls"
+    ))]
+    pub fn add_root_certificate(mut self, certificate: reqwest::Certificate) -> Self {
+        self.builder = self.builder.add_root_certificate(certificate);
+        self
+    }
+
+    pub fn user_agent(self, agent: HeaderValue) -> Self {
+        self.default_header(USER_AGENT, agent)
+    }
+
+    /// Header to send with every request
+    pub fn default_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
 }
 
 impl Client {
+    /// Client using the default HTTP transport
+    #[cfg(feature = "reqwest")]
     pub fn new(base: Url, token: String) -> Client {
-        let client = reqwest::Client::new();
+        Self::with_transport(base, token, HttpTransport::default())
+    }
+
+    pub fn with_transport<T: Transport + 'static>(
+        base: Url,
+        token: String,
+        transport: T,
+    ) -> Client {
         Client {
             base,
             token,
-            client,
+            transport: Arc::new(transport),
+            retry: None,
+            limiter: None,
+            middleware: Middlewares::default(),
+            encoding: Encoding::default(),
+        }
+    }
+
+    pub fn builder(base: Url, token: String) -> ClientBuilder {
+        ClientBuilder {
+            base,
+            token,
+            transport: None,
+            #[cfg(feature = "reqwest")]
+            client: None,
+            #[cfg(feature = "reqwest")]
+            builder: reqwest::Client::builder(),
+            #[cfg(feature = "reqwest")]
+            headers: HeaderMap::new(),
+            retry: None,
+            rate_limit: None,
+            middleware: Middlewares::default(),
+            encoding: Encoding::default(),
         }
     }
 
+    pub fn base(&self) -> &Url {
+        &self.base
+    }
+
     pub async fn request<R>(&self, request: &R) -> Result<R::Reply, RequestError>
     where
         R: ApiRequest,
     {
-        let u = self.base.join(request.route()).unwrap();
-        let t = Request {
-            token: &self.token,
-            request,
+        let mut attempt = 1;
+        loop {
+            match self.request_once(request, attempt).await {
+                Err(e) => match &self.retry {
+                    Some(retry) if retry.should_retry(R::READ_ONLY, attempt, &e) => {
+                        tokio::time::sleep(retry.backoff(attempt)).await;
+                        attempt += 1;
+                    }
+                    _ => return Err(e),
+                },
+                r => return r,
+            }
+        }
+    }
+
+    /// Call any method by name, e.g. `maniphest.search`, for methods
+    /// without a typed request. Like other writes these are only retried if
+    /// the retry policy allows for that.
+    pub async fn call_raw(&self, method: &str, params: Value) -> Result<Value, RequestError> {
+        let route = format!("api/{}", method);
+        // Keep the call within the API endpoint
+        if method.is_empty() || method.contains('/') || method.contains("..") {
+            return Err(RequestError::Encode {
+                route,
+                source: format!("Invalid method name `{}`", method).into(),
+            });
+        }
+        let call = RawCall { route, params };
+        self.request(&call).await
+    }
+
+    async fn request_once<R>(&self, request: &R, attempt: u32) -> Result<R::Reply, RequestError>
+    where
+        R: ApiRequest,
+    {
+        let call = self.call(request, attempt);
+        #[cfg(feature = "tracing")]
+        let call = {
+            use tracing::Instrument;
+            call.instrument(tracing::debug_span!(
+                "conduit",
+                route = request.route(),
+                attempt
+            ))
+        };
+        call.await
+    }
+
+    async fn call<R>(&self, request: &R, attempt: u32) -> Result<R::Reply, RequestError>
+    where
+        R: ApiRequest,
+    {
+        let route = request.route();
+        let _permit = match &self.limiter {
+            Some(limiter) => limiter.acquire().await,
+            None => None,
         };
 
-        let response = self.client.post(u).form(&t).send().await?;
-        let reply: Reply<R::Reply> = response.error_for_status()?.json().await?;
+        let start = Instant::now();
+        let result = self.send(request).await;
+        let duration = start.elapsed();
+
+        let completed = CompletedCall {
+            route,
+            attempt,
+            duration,
+            error: result.as_ref().err(),
+        };
+        for m in &self.middleware.0 {
+            m.after(&completed);
+        }
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(_) => tracing::debug!(?duration, "Call completed"),
+            Err(e) => tracing::debug!(?duration, error = %e, "Call failed"),
+        }
+        result
+    }
+
+    async fn send<R>(&self, request: &R) -> Result<R::Reply, RequestError>
+    where
+        R: ApiRequest,
+    {
+        let route = request.route();
+        let params = match R::ENCODING.unwrap_or(self.encoding) {
+            Encoding::Form => self.form_params(request),
+            Encoding::Json => self.json_params(request),
+        }
+        .map_err(|source| RequestError::Encode {
+            route: route.to_string(),
+            source,
+        })?;
+        let mut call = OutgoingCall {
+            route: route.to_string(),
+            params,
+            headers: HeaderMap::new(),
+        };
+        for m in &self.middleware.0 {
+            m.before(&mut call);
+        }
+
+        let form = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(&call.params)
+            .finish();
+        let t = TransportRequest {
+            url: self.base.join(&call.route).unwrap(),
+            route,
+            form,
+            headers: call.headers,
+        };
+        let mut body = self.transport.send(t).await?;
+        for m in &self.middleware.0 {
+            m.reply(route, &mut body);
+        }
+        let reply: Reply<R::Reply> = serde_json::from_slice(&body)
+            .map_err(|e| RequestError::deserialize(route, e, &body))?;
 
         match reply {
-            Reply {
-                result: Some(r), ..
-            } => Ok(r),
             Reply {
                 error_code: Some(code),
-                error_info: Some(info),
+                error_info,
                 ..
-            } => Err(RequestError::Api { code, info }),
-            _ => Err(RequestError::Incomplete),
+            } => Err(RequestError::Api {
+                route: route.to_string(),
+                code: code.into(),
+                info: error_info.unwrap_or_default(),
+            }),
+            Reply {
+                result: Some(r), ..
+            } => Ok(r),
+            // A null result, e.g. of a method without a return value, is only
+            // complete if the reply type allows for it
+            _ => serde_json::from_value(Value::Null).map_err(|_| RequestError::Incomplete {
+                route: route.to_string(),
+            }),
+        }
+    }
+
+    fn form_params<R: Serialize>(&self, request: &R) -> Result<Vec<(String, String)>, BoxError> {
+        let t = Request {
+            token: &self.token,
+            request,
+        };
+        let form = serde_urlencoded::to_string(&t)?;
+        Ok(url::form_urlencoded::parse(form.as_bytes())
+            .into_owned()
+            .collect())
+    }
+
+    fn json_params<R: Serialize>(&self, request: &R) -> Result<Vec<(String, String)>, BoxError> {
+        let mut params = match serde_json::to_value(request)? {
+            Value::Object(params) => params,
+            Value::Null => Default::default(),
+            _ => return Err("Request parameters aren't a map".into()),
+        };
+        // Unset options are left out of the form encoding as well
+        params.retain(|_, v| !strip_nulls(v));
+        params.insert(
+            "__conduit__".to_string(),
+            serde_json::json!({ "token": self.token }),
+        );
+        Ok(vec![
+            ("params".to_string(), Value::Object(params).to_string()),
+            ("output".to_string(), "json".to_string()),
+            ("__conduit__".to_string(), "1".to_string()),
+        ])
+    }
+}
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// Removes nulls from objects; returns whether the value itself is null
+fn strip_nulls(value: &mut Value) -> bool {
+    match value {
+        Value::Null => return true,
+        Value::Object(m) => m.retain(|_, v| !strip_nulls(v)),
+        Value::Array(a) => a.iter_mut().for_each(|v| {
+            strip_nulls(v);
+        }),
+        _ => (),
+    }
+    false
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use crate::phid::Lookup;
+    use crate::ErrorCode;
+    use phabricator_mock::PhabMockServer;
+
+    #[tokio::test]
+    async fn builder() {
+        let m = PhabMockServer::start().await;
+        let client = Client::builder(m.uri(), m.token().to_string())
+            .timeout(Duration::from_secs(10))
+            .connect_timeout(Duration::from_secs(5))
+            .no_proxy()
+            .user_agent(HeaderValue::from_static("phabricator-test"))
+            .default_header(
+                HeaderName::from_static("x-extra"),
+                HeaderValue::from_static("extra"),
+            )
+            .build()
+            .unwrap();
+
+        let l = Lookup {
+            names: vec!["T1".to_string()],
+        };
+        assert!(client.request(&l).await.unwrap().0.is_empty());
+
+        let requests = m.requests().await.unwrap();
+        let header = |name: &str| {
+            requests[0]
+                .headers
+                .iter()
+                .find(|(n, _)| n.as_str() == name)
+                .map(|(_, v)| v.last().as_str().to_string())
+        };
+        assert_eq!(Some("phabricator-test".to_string()), header("user-agent"));
+        assert_eq!(Some("extra".to_string()), header("x-extra"));
+    }
+
+    #[tokio::test]
+    async fn retry() {
+        let m = PhabMockServer::start().await;
+        let policy = RetryPolicy::new()
+            .max_attempts(3)
+            .initial_backoff(Duration::from_millis(1));
+        let client = Client::builder(m.uri(), m.token().to_string())
+            .retry(policy)
+            .build()
+            .unwrap();
+        let l = Lookup {
+            names: vec!["T1".to_string()],
+        };
+
+        m.fail_requests(2, 503);
+        assert!(client.request(&l).await.is_ok());
+        assert_eq!(3, m.n_requests().await);
+
+        m.fail_requests(3, 503);
+        assert!(client.request(&l).await.is_err());
+        assert_eq!(6, m.n_requests().await);
+
+        // Client errors aren't transient
+        m.fail_requests(1, 400);
+        assert!(client.request(&l).await.is_err());
+        assert_eq!(7, m.n_requests().await);
+    }
+
+    #[tokio::test]
+    async fn errors() {
+        let m = PhabMockServer::start().await;
+        let l = Lookup {
+            names: vec!["T1".to_string()],
+        };
+
+        let client = Client::new(m.uri(), "wrong".to_string());
+        let e = client.request(&l).await.unwrap_err();
+        assert!(e.is_auth());
+        assert!(matches!(
+            e,
+            RequestError::Status { route, status } if route == "api/phid.lookup" && status == 403
+        ));
+
+        let client = Client::new(m.uri(), m.token().to_string());
+        let i = crate::maniphest::info::Info { task_id: 404 };
+        let e = client.request(&i).await.unwrap_err();
+        assert_eq!(Some(&ErrorCode::BadTask), e.code());
+        assert!(!e.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn json_encoding() {
+        use crate::maniphest::search::{Attachments, Constraints, Search};
+
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        m.new_simple_task(100, &user);
+        m.new_simple_task(200, &user);
+        let client = Client::builder(m.uri(), m.token().to_string())
+            .encoding(Encoding::Json)
+            .build()
+            .unwrap();
+
+        let s = Search {
+            constraints: Constraints {
+                ids: Some(vec![100, 200]),
+                ..Default::default()
+            },
+            attachments: Attachments {
+                projects: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let r = client.request(&s).await.unwrap();
+        assert_eq!(2, r.data.len());
+        assert!(r.data[0].attachments.projects.is_some());
+
+        let requests = m.requests().await.unwrap();
+        let body = String::from_utf8_lossy(&requests[0].body);
+        assert!(body.starts_with("params="), "{}", body);
+        assert!(body.contains("output=json"), "{}", body);
+        assert!(!body.contains("api.token"), "{}", body);
+
+        let client = Client::builder(m.uri(), "wrong".to_string())
+            .encoding(Encoding::Json)
+            .build()
+            .unwrap();
+        assert!(client.request(&s).await.unwrap_err().is_auth());
+    }
+
+    #[tokio::test]
+    async fn raw() {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        let task = m.new_simple_task(1, &user);
+
+        for encoding in [Encoding::Form, Encoding::Json] {
+            let client = Client::builder(m.uri(), m.token().to_string())
+                .encoding(encoding)
+                .build()
+                .unwrap();
+            let params = serde_json::json!({
+                "constraints": { "ids": [1] },
+                "attachments": { "projects": true },
+            });
+            let r = client.call_raw("maniphest.search", params).await.unwrap();
+            assert_eq!(1, r["data"][0]["id"]);
+            assert_eq!(task.phid.to_string(), r["data"][0]["phid"]);
+            assert!(r["data"][0]["attachments"]["projects"].is_object());
         }
+
+        let client = Client::new(m.uri(), m.token().to_string());
+        let methods = client
+            .call_raw("conduit.query", serde_json::json!({}))
+            .await
+            .unwrap();
+        assert!(methods["maniphest.search"]["params"].is_object());
+        let e = client
+            .call_raw("maniphest.info", serde_json::json!({ "task_id": 404 }))
+            .await
+            .unwrap_err();
+        assert_eq!(Some(&ErrorCode::BadTask), e.code());
+    }
+
+    #[derive(Serialize)]
+    struct JsonLookup {
+        names: Vec<String>,
+    }
+
+    impl ApiRequest for JsonLookup {
+        type Reply = crate::phid::LookupResult;
+        const ROUTE: &'static str = "api/phid.lookup";
+        const READ_ONLY: bool = true;
+        const ENCODING: Option<Encoding> = Some(Encoding::Json);
+    }
+
+    #[tokio::test]
+    async fn request_encoding() {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        m.new_simple_task(1, &user);
+        let client = Client::new(m.uri(), m.token().to_string());
+
+        let l = JsonLookup {
+            names: vec!["T1".to_string()],
+        };
+        let r = client.request(&l).await.unwrap();
+        assert!(r.0.contains_key("T1"));
+        let l = Lookup {
+            names: vec!["T1".to_string()],
+        };
+        assert!(client.request(&l).await.unwrap().0.contains_key("T1"));
+
+        let requests = m.requests().await.unwrap();
+        assert!(requests[0].body.starts_with(b"params="));
+        assert!(requests[1].body.starts_with(b"api.token="));
     }
 }
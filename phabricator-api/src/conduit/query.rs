@@ -0,0 +1,48 @@
+use crate::utils::map_or_empty;
+use crate::ApiRequest;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// List all methods the server exposes
+#[derive(Debug, Default, Serialize)]
+pub struct Query {}
+
+#[derive(Deserialize, Debug)]
+pub struct Method {
+    pub description: String,
+    /// Parameter types by name, e.g. `optional list<int>`
+    #[serde(deserialize_with = "map_or_empty")]
+    pub params: HashMap<String, String>,
+    #[serde(rename = "return")]
+    pub returns: String,
+}
+
+/// Methods by name, e.g. `maniphest.search`
+#[derive(Deserialize, Debug)]
+#[serde(transparent)]
+pub struct QueryResult(#[serde(deserialize_with = "map_or_empty")] pub HashMap<String, Method>);
+
+impl ApiRequest for Query {
+    type Reply = QueryResult;
+    const ROUTE: &'static str = "api/conduit.query";
+    const READ_ONLY: bool = true;
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use phabricator_mock::PhabMockServer;
+
+    #[tokio::test]
+    async fn methods() {
+        let m = PhabMockServer::start().await;
+        let client = crate::Client::new(m.uri(), m.token().to_string());
+
+        let r = client.request(&Query {}).await.unwrap();
+        let search = &r.0["maniphest.search"];
+        assert_eq!("optional map<string, wild>", search.params["constraints"]);
+        assert_eq!("map<string, wild>", search.returns);
+        assert!(r.0["conduit.query"].params.is_empty());
+    }
+}
@@ -0,0 +1,311 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use url::Url;
+
+use crate::{Client, ClientBuilder};
+
+/// Environment variable overriding the server url
+pub const URL_ENV: &str = "PHABRICATOR_URL";
+/// Environment variable overriding the API token
+pub const TOKEN_ENV: &str = "PHABRICATOR_TOKEN";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read {0}: {1}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("Failed to parse {0}: {1}")]
+    Parse(PathBuf, #[source] serde_json::Error),
+    #[error("Invalid url {0}: {1}")]
+    Url(String, #[source] url::ParseError),
+    #[error("No Phabricator url configured")]
+    NoUrl,
+    #[error("No API token configured for {0}")]
+    NoToken(Url),
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ArcRc {
+    #[serde(default)]
+    hosts: HashMap<String, ArcRcHost>,
+    #[serde(default)]
+    config: ArcRcConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArcRcHost {
+    token: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ArcRcConfig {
+    default: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArcConfig {
+    #[serde(rename = "phabricator.uri")]
+    uri: Option<String>,
+    // Older name for the same setting
+    conduit_uri: Option<String>,
+}
+
+/// Server url and token to connect with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Base url of the server, e.g. `https://phabricator.example.com/`
+    pub url: Url,
+    pub token: String,
+}
+
+impl Config {
+    /// Load the configuration the way `arc` finds it; see [ConfigLoader]
+    pub fn load() -> Result<Config, ConfigError> {
+        ConfigLoader::new().load()
+    }
+
+    pub fn loader() -> ConfigLoader {
+        ConfigLoader::new()
+    }
+
+    #[cfg(feature = "reqwest")]
+    pub fn client(&self) -> Client {
+        Client::new(self.url.clone(), self.token.clone())
+    }
+
+    pub fn builder(&self) -> ClientBuilder {
+        Client::builder(self.url.clone(), self.token.clone())
+    }
+}
+
+/// Loads a [Config], with earlier sources taking precedence:
+///
+/// 1. The url and token set on the loader
+/// 2. `PHABRICATOR_URL` and `PHABRICATOR_TOKEN` in the environment
+/// 3. `phabricator.uri` from the nearest `.arcconfig`, searching upwards
+///    from the current directory
+/// 4. The default host from `~/.arcrc`, or the only host if there is just one
+///
+/// Unless given explicitly or in the environment, the token is taken from
+/// the `~/.arcrc` entry for the selected url.
+#[derive(Clone, Debug, Default)]
+pub struct ConfigLoader {
+    url: Option<String>,
+    token: Option<String>,
+    arcrc: Option<PathBuf>,
+    directory: Option<PathBuf>,
+    no_env: bool,
+}
+
+// Base url as used by the client; arc stores the api endpoint itself
+fn base_url(url: &str) -> Result<Url, ConfigError> {
+    let mut url = Url::parse(url).map_err(|e| ConfigError::Url(url.to_string(), e))?;
+    let path = url.path().trim_end_matches('/');
+    let path = path.strip_suffix("/api").unwrap_or(path);
+    let path = format!("{}/", path);
+    url.set_path(&path);
+    Ok(url)
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<Option<T>, ConfigError> {
+    match std::fs::read(path) {
+        Ok(data) => serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|e| ConfigError::Parse(path.to_path_buf(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(ConfigError::Io(path.to_path_buf(), e)),
+    }
+}
+
+impl ConfigLoader {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn url<S: Into<String>>(mut self, url: S) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    pub fn token<S: Into<String>>(mut self, token: S) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Read this file rather than `~/.arcrc`
+    pub fn arcrc<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.arcrc = Some(path.into());
+        self
+    }
+
+    /// Search for `.arcconfig` from this directory rather than the current one
+    pub fn directory<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.directory = Some(path.into());
+        self
+    }
+
+    /// Ignore environment variables
+    pub fn no_env(mut self) -> Self {
+        self.no_env = true;
+        self
+    }
+
+    fn arcrc_path(&self) -> Option<PathBuf> {
+        self.arcrc.clone().or_else(|| {
+            let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+            Some(Path::new(&home).join(".arcrc"))
+        })
+    }
+
+    fn arcconfig_url(&self) -> Result<Option<String>, ConfigError> {
+        let start = match &self.directory {
+            Some(d) => d.clone(),
+            None => match std::env::current_dir() {
+                Ok(d) => d,
+                Err(_) => return Ok(None),
+            },
+        };
+        for dir in start.ancestors() {
+            if let Some(c) = read_json::<ArcConfig>(&dir.join(".arcconfig"))? {
+                return Ok(c.uri.or(c.conduit_uri));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        self.load_with(|var| {
+            if self.no_env {
+                None
+            } else {
+                std::env::var(var).ok()
+            }
+        })
+    }
+
+    fn load_with<E>(&self, env: E) -> Result<Config, ConfigError>
+    where
+        E: Fn(&str) -> Option<String>,
+    {
+        let arcrc: ArcRc = match self.arcrc_path() {
+            Some(path) => read_json(&path)?.unwrap_or_default(),
+            None => ArcRc::default(),
+        };
+
+        let url = match self.url.clone().or_else(|| env(URL_ENV)) {
+            Some(url) => url,
+            None => match self.arcconfig_url()? {
+                Some(url) => url,
+                None => match (&arcrc.config.default, arcrc.hosts.len()) {
+                    (Some(default), _) => default.clone(),
+                    (None, 1) => arcrc.hosts.keys().next().unwrap().clone(),
+                    _ => return Err(ConfigError::NoUrl),
+                },
+            },
+        };
+        let url = base_url(&url)?;
+
+        let token = match self.token.clone().or_else(|| env(TOKEN_ENV)) {
+            Some(token) => token,
+            None => arcrc
+                .hosts
+                .iter()
+                .find(|(host, _)| base_url(host).ok().as_ref() == Some(&url))
+                .and_then(|(_, h)| h.token.clone())
+                .ok_or_else(|| ConfigError::NoToken(url.clone()))?,
+        };
+
+        Ok(Config { url, token })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ARCRC: &str = r#"{
+        "hosts": {
+            "https://phab.example.com/api/": { "token": "api-example" },
+            "https://other.example.org/phab/api/": { "token": "api-other" }
+        },
+        "config": { "default": "https://phab.example.com" }
+    }"#;
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn arcrc() {
+        let dir = tempfile::tempdir().unwrap();
+        let arcrc = dir.path().join(".arcrc");
+        std::fs::write(&arcrc, ARCRC).unwrap();
+        let loader = ConfigLoader::new().arcrc(&arcrc).directory(dir.path());
+
+        let config = loader.load_with(no_env).unwrap();
+        assert_eq!("https://phab.example.com/", config.url.as_str());
+        assert_eq!("api-example", config.token);
+
+        let config = loader
+            .clone()
+            .url("https://other.example.org/phab/")
+            .load_with(no_env)
+            .unwrap();
+        assert_eq!("https://other.example.org/phab/", config.url.as_str());
+        assert_eq!("api-other", config.token);
+
+        assert!(matches!(
+            loader
+                .clone()
+                .url("https://unknown.example.com")
+                .load_with(no_env),
+            Err(ConfigError::NoToken(_))
+        ));
+        assert!(matches!(
+            ConfigLoader::new()
+                .arcrc(dir.path().join("missing"))
+                .directory(dir.path())
+                .load_with(no_env),
+            Err(ConfigError::NoUrl)
+        ));
+    }
+
+    #[test]
+    fn precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let arcrc = dir.path().join(".arcrc");
+        std::fs::write(&arcrc, ARCRC).unwrap();
+        let project = dir.path().join("project");
+        let subdir = project.join("src");
+        std::fs::create_dir_all(&subdir).unwrap();
+        std::fs::write(
+            project.join(".arcconfig"),
+            r#"{ "phabricator.uri": "https://other.example.org/phab/" }"#,
+        )
+        .unwrap();
+        let loader = ConfigLoader::new().arcrc(&arcrc).directory(&subdir);
+
+        let config = loader.load_with(no_env).unwrap();
+        assert_eq!("https://other.example.org/phab/", config.url.as_str());
+        assert_eq!("api-other", config.token);
+
+        let env = |var: &str| match var {
+            URL_ENV => Some("https://phab.example.com/".to_string()),
+            TOKEN_ENV => Some("api-env".to_string()),
+            _ => None,
+        };
+        let config = loader.load_with(env).unwrap();
+        assert_eq!("https://phab.example.com/", config.url.as_str());
+        assert_eq!("api-env", config.token);
+
+        let config = loader
+            .clone()
+            .url("https://explicit.example.com")
+            .token("api-explicit")
+            .load_with(env)
+            .unwrap();
+        assert_eq!("https://explicit.example.com/", config.url.as_str());
+        assert_eq!("api-explicit", config.token);
+    }
+}
@@ -46,14 +46,16 @@ pub struct SearchResult {
 impl ApiRequest for Search {
     type Reply = SearchResult;
     const ROUTE: &'static str = "api/edge.search";
+    const READ_ONLY: bool = true;
 }
 
 impl ApiRequest for SearchCursor<'_> {
     type Reply = SearchResult;
     const ROUTE: &'static str = "api/edge.search";
+    const READ_ONLY: bool = true;
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "reqwest"))]
 mod test {
     use super::*;
     use crate::types::Phid;
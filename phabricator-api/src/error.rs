@@ -0,0 +1,246 @@
+use http::StatusCode;
+use thiserror::Error;
+
+/// Conduit error codes, with the well-known ones mapped to variants
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// `ERR-INVALID-AUTH`: missing or invalid API token
+    InvalidAuth,
+    /// `ERR-INVALID-SESSION`: the session or token expired
+    InvalidSession,
+    /// `ERR-CONDUIT-CALL`: unknown method or malformed call
+    ConduitCall,
+    /// `ERR-CONDUIT-CORE`: generic failure, e.g. invalid parameters
+    ConduitCore,
+    /// `ERR_BAD_TASK`: no such task
+    BadTask,
+    /// `ERR-RATE-LIMIT`: too many requests
+    RateLimit,
+    /// `ERR-NOT-FOUND`: no such object
+    NotFound,
+    /// `ERR-INVALID-PARAMETER`
+    InvalidParameter,
+    Other(String),
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ErrorCode::InvalidAuth => "ERR-INVALID-AUTH",
+            ErrorCode::InvalidSession => "ERR-INVALID-SESSION",
+            ErrorCode::ConduitCall => "ERR-CONDUIT-CALL",
+            ErrorCode::ConduitCore => "ERR-CONDUIT-CORE",
+            ErrorCode::BadTask => "ERR_BAD_TASK",
+            ErrorCode::RateLimit => "ERR-RATE-LIMIT",
+            ErrorCode::NotFound => "ERR-NOT-FOUND",
+            ErrorCode::InvalidParameter => "ERR-INVALID-PARAMETER",
+            ErrorCode::Other(code) => code,
+        }
+    }
+}
+
+impl From<String> for ErrorCode {
+    fn from(code: String) -> Self {
+        match code.as_str() {
+            "ERR-INVALID-AUTH" => ErrorCode::InvalidAuth,
+            "ERR-INVALID-SESSION" => ErrorCode::InvalidSession,
+            "ERR-CONDUIT-CALL" => ErrorCode::ConduitCall,
+            "ERR-CONDUIT-CORE" => ErrorCode::ConduitCore,
+            "ERR_BAD_TASK" => ErrorCode::BadTask,
+            "ERR-RATE-LIMIT" => ErrorCode::RateLimit,
+            "ERR-NOT-FOUND" => ErrorCode::NotFound,
+            "ERR-INVALID-PARAMETER" => ErrorCode::InvalidParameter,
+            _ => ErrorCode::Other(code),
+        }
+    }
+}
+
+impl From<&str> for ErrorCode {
+    fn from(code: &str) -> Self {
+        ErrorCode::from(code.to_string())
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RequestError {
+    #[error("API Error from {route} ({code}): {info}")]
+    Api {
+        route: String,
+        code: ErrorCode,
+        info: String,
+    },
+    #[error("Failed to encode request for {route}: {source}")]
+    Encode {
+        route: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("HTTP status {status} from {route}")]
+    Status { route: String, status: StatusCode },
+    #[error("Failed to parse reply from {route}: {source} near `{snippet}`")]
+    Deserialize {
+        route: String,
+        #[source]
+        source: serde_json::Error,
+        /// Part of the reply around the failure
+        snippet: String,
+    },
+    #[error("Incomplete reply from {route}")]
+    Incomplete { route: String },
+    #[error("Transport failure for {route}: {source}")]
+    Transport {
+        route: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[cfg(feature = "reqwest")]
+    #[error("Request failure for {route}: {source}")]
+    Request {
+        route: String,
+        #[source]
+        source: reqwest::Error,
+    },
+}
+
+impl RequestError {
+    /// Whether the failure is likely transient, so the request may succeed
+    /// when retried
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            RequestError::Api { code, .. } => *code == ErrorCode::RateLimit,
+            RequestError::Status { status, .. } => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            #[cfg(feature = "reqwest")]
+            RequestError::Request { source, .. } => source.is_timeout() || source.is_connect(),
+            RequestError::Encode { .. }
+            | RequestError::Transport { .. }
+            | RequestError::Deserialize { .. }
+            | RequestError::Incomplete { .. } => false,
+        }
+    }
+
+    /// Whether the server rejected the credentials
+    pub fn is_auth(&self) -> bool {
+        match self {
+            RequestError::Api { code, .. } => {
+                matches!(code, ErrorCode::InvalidAuth | ErrorCode::InvalidSession)
+            }
+            RequestError::Status { status, .. } => {
+                *status == StatusCode::UNAUTHORIZED || *status == StatusCode::FORBIDDEN
+            }
+            _ => false,
+        }
+    }
+
+    /// The Conduit error code, for API errors
+    pub fn code(&self) -> Option<&ErrorCode> {
+        match self {
+            RequestError::Api { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn deserialize(route: &str, source: serde_json::Error, body: &[u8]) -> Self {
+        let snippet = snippet(&String::from_utf8_lossy(body), &source);
+        RequestError::Deserialize {
+            route: route.to_string(),
+            source,
+            snippet,
+        }
+    }
+}
+
+// Up to 40 characters on either side of the error position
+fn snippet(body: &str, e: &serde_json::Error) -> String {
+    const CONTEXT: usize = 40;
+    let offset = body
+        .split_inclusive('\n')
+        .take(e.line().saturating_sub(1))
+        .map(str::len)
+        .sum::<usize>()
+        + e.column().saturating_sub(1);
+
+    let chars: Vec<(usize, char)> = body.char_indices().collect();
+    let pos = chars
+        .iter()
+        .position(|(i, _)| *i >= offset)
+        .unwrap_or(chars.len());
+    let start = pos.saturating_sub(CONTEXT);
+    let end = (pos + CONTEXT).min(chars.len());
+    chars[start..end].iter().map(|(_, c)| c).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn codes() {
+        for code in &[
+            "ERR-INVALID-AUTH",
+            "ERR-INVALID-SESSION",
+            "ERR-CONDUIT-CALL",
+            "ERR-CONDUIT-CORE",
+            "ERR_BAD_TASK",
+            "ERR-RATE-LIMIT",
+            "ERR-NOT-FOUND",
+            "ERR-INVALID-PARAMETER",
+            "ERR-SOMETHING-ELSE",
+        ] {
+            assert_eq!(*code, ErrorCode::from(*code).as_str());
+        }
+        assert_eq!(ErrorCode::BadTask, ErrorCode::from("ERR_BAD_TASK"));
+        assert_eq!(
+            ErrorCode::Other("ERR-UNKNOWN".to_string()),
+            ErrorCode::from("ERR-UNKNOWN")
+        );
+    }
+
+    #[test]
+    fn deserialize_snippet() {
+        let body = format!(
+            "{{\n  \"result\": {{ \"padding\": \"{}\", \"id\": x }}\n}}",
+            "a".repeat(60)
+        );
+        let source = serde_json::from_str::<serde_json::Value>(&body).unwrap_err();
+        let e = RequestError::deserialize("api/test", source, body.as_bytes());
+        match e {
+            RequestError::Deserialize { route, snippet, .. } => {
+                assert_eq!("api/test", route);
+                assert!(snippet.ends_with("\"id\": x }\n}"), "{}", snippet);
+                assert!(snippet.starts_with("aaa"), "{}", snippet);
+                assert!(snippet.chars().count() <= 80);
+            }
+            e => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn classify() {
+        let api = |code: &str| RequestError::Api {
+            route: "api/test".to_string(),
+            code: code.into(),
+            info: String::new(),
+        };
+        let status = |status: StatusCode| RequestError::Status {
+            route: "api/test".to_string(),
+            status,
+        };
+        assert!(api("ERR-RATE-LIMIT").is_retryable());
+        assert!(!api("ERR-CONDUIT-CORE").is_retryable());
+        assert!(api("ERR-INVALID-AUTH").is_auth());
+        assert!(api("ERR-INVALID-SESSION").is_auth());
+        assert!(!api("ERR-NOT-FOUND").is_auth());
+        assert!(status(StatusCode::BAD_GATEWAY).is_retryable());
+        assert!(status(StatusCode::TOO_MANY_REQUESTS).is_retryable());
+        assert!(!status(StatusCode::BAD_REQUEST).is_retryable());
+        assert!(status(StatusCode::FORBIDDEN).is_auth());
+    }
+}
@@ -1,9 +1,22 @@
+#[cfg(feature = "blocking")]
+pub mod blocking;
 mod client;
+pub mod conduit;
+pub mod config;
 pub mod edge;
+mod error;
+pub use error::{ErrorCode, RequestError};
 pub mod maniphest;
+pub mod middleware;
 pub mod phid;
 pub mod project;
+pub mod remarkup;
+mod retry;
+pub use retry::{RateLimit, RetryPolicy};
 mod ser;
+pub mod transaction;
+pub mod transport;
 pub mod types;
+pub mod vcr;
 pub use client::*;
 mod utils;
@@ -0,0 +1,199 @@
+use crate::types::Phid;
+use crate::ApiRequest;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize, Serializer};
+
+// Conduit can't express null in form encoding; an empty owner unassigns
+fn serialize_owner<S>(owner: &Option<Phid>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match owner {
+        Some(owner) => owner.serialize(s),
+        None => s.serialize_str(""),
+    }
+}
+
+fn serialize_points<S>(points: &Option<Decimal>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match points {
+        Some(points) => s.serialize_str(&points.to_string()),
+        None => s.serialize_str(""),
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", content = "value")]
+pub enum Transaction {
+    #[serde(rename = "title")]
+    Title(String),
+    #[serde(rename = "description")]
+    Description(String),
+    /// `None` removes the owner
+    #[serde(rename = "owner", serialize_with = "serialize_owner")]
+    Owner(Option<Phid>),
+    /// Status value, e.g. `resolved`
+    #[serde(rename = "status")]
+    Status(String),
+    /// Priority keyword, e.g. `high`
+    #[serde(rename = "priority")]
+    Priority(String),
+    #[serde(rename = "points", serialize_with = "serialize_points")]
+    Points(Option<Decimal>),
+    #[serde(rename = "projects.add")]
+    AddProjects(Vec<Phid>),
+    #[serde(rename = "projects.remove")]
+    RemoveProjects(Vec<Phid>),
+    #[serde(rename = "projects.set")]
+    SetProjects(Vec<Phid>),
+    #[serde(rename = "parents.add")]
+    AddParents(Vec<Phid>),
+    #[serde(rename = "parents.remove")]
+    RemoveParents(Vec<Phid>),
+    #[serde(rename = "parents.set")]
+    SetParents(Vec<Phid>),
+    #[serde(rename = "subtasks.add")]
+    AddSubtasks(Vec<Phid>),
+    #[serde(rename = "subtasks.remove")]
+    RemoveSubtasks(Vec<Phid>),
+    #[serde(rename = "subtasks.set")]
+    SetSubtasks(Vec<Phid>),
+    #[serde(rename = "comment")]
+    Comment(String),
+}
+
+/// Create a task, or edit an existing one when `object` is set
+#[derive(Serialize, Debug, Default)]
+pub struct Edit {
+    /// Id, monogram (e.g. `T123`) or phid of the task to edit
+    #[serde(rename = "objectIdentifier")]
+    pub object: Option<String>,
+    pub transactions: Vec<Transaction>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EditObject {
+    pub id: u32,
+    pub phid: Phid,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EditTransaction {
+    pub phid: Phid,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EditResult {
+    pub object: EditObject,
+    pub transactions: Vec<EditTransaction>,
+}
+
+impl ApiRequest for Edit {
+    type Reply = EditResult;
+    const ROUTE: &'static str = "api/maniphest.edit";
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use crate::RequestError;
+    use phabricator_mock::PhabMockServer;
+
+    #[tokio::test]
+    async fn create() {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        let parent = m.new_simple_task(100, &user);
+
+        let client = crate::Client::new(m.uri(), m.token().to_string());
+        let e = Edit {
+            object: None,
+            transactions: vec![
+                Transaction::Title("New task".to_string()),
+                Transaction::Description("Some **description**".to_string()),
+                Transaction::Owner(Some(Phid(user.phid.to_string()))),
+                Transaction::Points(Some(Decimal::new(15, 1))),
+                Transaction::AddParents(vec![Phid(parent.phid.to_string())]),
+                Transaction::Comment("Created".to_string()),
+            ],
+        };
+        let r = client.request(&e).await.unwrap();
+        assert_eq!(101, r.object.id);
+        assert_eq!(6, r.transactions.len());
+
+        let t = m.get_task(101).unwrap();
+        assert_eq!(r.object.phid.0, t.phid.to_string());
+        assert_eq!("New task", t.full_name);
+        assert_eq!("Some **description**", t.description);
+        assert_eq!(user.phid, t.owner.as_ref().unwrap().phid);
+        assert_eq!(Some(Decimal::new(15, 1)), t.points);
+        assert_eq!(1, t.comments().len());
+        assert_eq!(t.phid, m.get_task(100).unwrap().subtasks()[0].phid);
+    }
+
+    #[tokio::test]
+    async fn edit() {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        let t = m.new_simple_task(100, &user);
+        let sub = m.new_simple_task(200, &user);
+        phabricator_mock::task::link(&t, &sub);
+
+        let client = crate::Client::new(m.uri(), m.token().to_string());
+        let e = Edit {
+            object: Some("T100".to_string()),
+            transactions: vec![
+                Transaction::Title("Renamed".to_string()),
+                Transaction::Owner(None),
+                Transaction::Status("closed".to_string()),
+            ],
+        };
+        client.request(&e).await.unwrap();
+
+        let t = m.get_task(100).unwrap();
+        assert_eq!("Renamed", t.full_name);
+        assert!(t.owner.is_none());
+        assert!(t.status.closed);
+        assert!(t.date_closed.is_some());
+        // Relations survive the edit
+        assert_eq!(sub.phid, t.subtasks()[0].phid);
+        assert_eq!(t.phid, m.get_task(200).unwrap().parents()[0].phid);
+
+        let e = Edit {
+            object: Some(t.phid.to_string()),
+            transactions: vec![Transaction::RemoveSubtasks(vec![Phid(
+                sub.phid.to_string(),
+            )])],
+        };
+        client.request(&e).await.unwrap();
+        assert!(m.get_task(100).unwrap().subtasks().is_empty());
+        assert!(m.get_task(200).unwrap().parents().is_empty());
+    }
+
+    #[tokio::test]
+    async fn invalid() {
+        let m = PhabMockServer::start().await;
+        m.new_user("user", "Test User");
+        let client = crate::Client::new(m.uri(), m.token().to_string());
+
+        let e = Edit {
+            object: Some("T404".to_string()),
+            transactions: vec![Transaction::Title("Renamed".to_string())],
+        };
+        assert!(matches!(
+            client.request(&e).await,
+            Err(RequestError::Api { .. })
+        ));
+
+        let e = Edit {
+            object: None,
+            transactions: vec![Transaction::Status("unknown".to_string())],
+        };
+        assert!(matches!(
+            client.request(&e).await,
+            Err(RequestError::Api { .. })
+        ));
+    }
+}
@@ -65,9 +65,10 @@ pub struct InfoResult {
 impl ApiRequest for Info {
     type Reply = InfoResult;
     const ROUTE: &'static str = "api/maniphest.info";
+    const READ_ONLY: bool = true;
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "reqwest"))]
 mod test {
     use super::*;
     use phabricator_mock::task::Task;
@@ -1,6 +1,7 @@
 use serde::de::Deserializer;
 use serde::Deserialize;
 
+pub mod edit;
 pub mod info;
 pub mod search;
 
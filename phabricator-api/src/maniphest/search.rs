@@ -1,10 +1,12 @@
 use crate::types::Phid;
-use crate::utils::{deserialize_timestamp, deserialize_timestamp_option};
+use crate::utils::{
+    deserialize_raw_string, deserialize_timestamp, deserialize_timestamp_option, map_or_empty,
+    serialize_timestamp_option,
+};
 use crate::ApiRequest;
 use chrono::DateTime;
 use chrono::Utc;
 use rust_decimal::prelude::*;
-use serde::de::Deserializer;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json::value::Value as JsonValue;
@@ -12,18 +14,6 @@ use std::collections::HashMap;
 use std::default::Default;
 use std::ops::Not;
 
-fn deserialize_raw_string<'de, D>(d: D) -> Result<String, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    #[derive(Deserialize)]
-    struct Raw {
-        raw: String,
-    }
-    let r = Raw::deserialize(d)?;
-    Ok(r.raw)
-}
-
 pub trait Serializable: erased_serde::Serialize + std::fmt::Debug {}
 impl<T: erased_serde::Serialize + std::fmt::Debug> Serializable for T {}
 erased_serde::serialize_trait_object!(Serializable);
@@ -34,6 +24,13 @@ pub struct Constraints {
     pub phids: Option<Vec<Phid>>,
     pub query: Option<String>,
     pub projects: Option<Vec<String>>,
+    #[serde(
+        rename = "modifiedStart",
+        serialize_with = "serialize_timestamp_option"
+    )]
+    pub modified_start: Option<DateTime<Utc>>,
+    #[serde(rename = "modifiedEnd", serialize_with = "serialize_timestamp_option")]
+    pub modified_end: Option<DateTime<Utc>>,
     #[serde(flatten)]
     pub custom: Option<HashMap<String, Box<dyn Serializable + Send + Sync>>>,
 }
@@ -124,6 +121,7 @@ pub struct Columns {
 
 #[derive(Deserialize, Debug)]
 pub struct Boards {
+    #[serde(deserialize_with = "map_or_empty")]
     pub boards: HashMap<Phid, Columns>,
 }
 
@@ -137,14 +135,16 @@ pub struct AttachmentsResult {
 impl ApiRequest for Search {
     type Reply = SearchResult;
     const ROUTE: &'static str = "api/maniphest.search";
+    const READ_ONLY: bool = true;
 }
 
 impl ApiRequest for SearchCursor<'_> {
     type Reply = SearchResult;
     const ROUTE: &'static str = "api/maniphest.search";
+    const READ_ONLY: bool = true;
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "reqwest"))]
 mod test {
     use super::*;
     use phabricator_mock::task::Task;
@@ -370,6 +370,32 @@ mod test {
         assert_eq!(column.phid.to_string(), c.phid.0);
     }
 
+    #[tokio::test]
+    async fn no_columns() {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        m.new_simple_task(100, &user);
+
+        let client = crate::Client::new(m.uri(), m.token().to_string());
+        let s = Search {
+            constraints: Constraints {
+                ids: Some(vec![100]),
+                ..Default::default()
+            },
+            attachments: Attachments {
+                columns: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let r = client.request(&s).await.unwrap();
+        assert_eq!(r.data.len(), 1);
+
+        let b = r.data[0].attachments.columns.as_ref().expect("No Columns");
+        assert!(b.boards.is_empty());
+    }
+
     #[tokio::test]
     async fn points() {
         let m = PhabMockServer::start().await;
@@ -0,0 +1,142 @@
+use crate::RequestError;
+use http::header::HeaderMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Hooks around every Conduit call made by a [Client](crate::Client). With
+/// retries enabled the hooks run for each attempt.
+pub trait Middleware: Send + Sync {
+    /// Inspect or change a call before it's sent
+    fn before(&self, _call: &mut OutgoingCall) {}
+
+    /// Inspect or change the raw JSON reply before it's parsed
+    fn reply(&self, _route: &str, _body: &mut Vec<u8>) {}
+
+    /// Called when a call finished, successfully or not
+    fn after(&self, _call: &CompletedCall<'_>) {}
+}
+
+// Allows keeping a handle on a middleware, e.g. to read collected metrics
+impl<M: Middleware> Middleware for Arc<M> {
+    fn before(&self, call: &mut OutgoingCall) {
+        (**self).before(call)
+    }
+
+    fn reply(&self, route: &str, body: &mut Vec<u8>) {
+        (**self).reply(route, body)
+    }
+
+    fn after(&self, call: &CompletedCall<'_>) {
+        (**self).after(call)
+    }
+}
+
+/// A call about to be sent
+#[derive(Debug)]
+pub struct OutgoingCall {
+    pub route: String,
+    /// Form parameters; these include the token, either as `api.token` or
+    /// in the JSON `params`, so take care when logging them
+    pub params: Vec<(String, String)>,
+    /// Headers added to this call
+    pub headers: HeaderMap,
+}
+
+#[derive(Debug)]
+pub struct CompletedCall<'a> {
+    pub route: &'a str,
+    /// Attempt number, starting at 1
+    pub attempt: u32,
+    pub duration: Duration,
+    pub error: Option<&'a RequestError>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Middlewares(pub(crate) Vec<Arc<dyn Middleware>>);
+
+impl std::fmt::Debug for Middlewares {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Middlewares({})", self.0.len())
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use crate::phid::Lookup;
+    use crate::Client;
+    use http::header::{HeaderName, HeaderValue};
+    use phabricator_mock::PhabMockServer;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder {
+        calls: Mutex<Vec<(String, u32, bool)>>,
+    }
+
+    impl Middleware for Recorder {
+        fn before(&self, call: &mut OutgoingCall) {
+            // Look up a different task than asked for
+            for (k, v) in &mut call.params {
+                if k == "names[0]" {
+                    *v = "T2".to_string();
+                }
+            }
+            call.headers.insert(
+                HeaderName::from_static("x-recorder"),
+                HeaderValue::from_static("1"),
+            );
+        }
+
+        fn reply(&self, _route: &str, body: &mut Vec<u8>) {
+            let replaced = String::from_utf8_lossy(body).replace("Second", "Changed");
+            *body = replaced.into_bytes();
+        }
+
+        fn after(&self, call: &CompletedCall<'_>) {
+            self.calls.lock().unwrap().push((
+                call.route.to_string(),
+                call.attempt,
+                call.error.is_some(),
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn hooks() {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        m.new_simple_task(1, &user);
+        let t2 = m.new_simple_task(2, &user);
+        m.update_task(2, |t| t.full_name = "Second".to_string());
+
+        let recorder = Arc::new(Recorder::default());
+        let client = Client::builder(m.uri(), m.token().to_string())
+            .middleware(recorder.clone())
+            .retry(crate::RetryPolicy::new().initial_backoff(Duration::from_millis(1)))
+            .build()
+            .unwrap();
+
+        m.fail_requests(1, 500);
+        let l = Lookup {
+            names: vec!["T1".to_string()],
+        };
+        let r = client.request(&l).await.unwrap();
+        let item = &r.0["T2"];
+        assert_eq!(t2.phid.to_string(), item.phid.0);
+        assert!(item.full_name.contains("Changed"));
+
+        assert_eq!(
+            vec![
+                ("api/phid.lookup".to_string(), 1, true),
+                ("api/phid.lookup".to_string(), 2, false)
+            ],
+            *recorder.calls.lock().unwrap()
+        );
+        let requests = m.requests().await.unwrap();
+        assert!(requests[1]
+            .headers
+            .iter()
+            .any(|(n, _)| n.as_str() == "x-recorder"));
+    }
+}
@@ -32,14 +32,15 @@ where
 
 #[derive(Deserialize, Debug)]
 #[serde(transparent)]
-pub struct LookupResult(#[serde(deserialize_with = "data_or_no_data")] HashMap<String, Item>);
+pub struct LookupResult(#[serde(deserialize_with = "data_or_no_data")] pub HashMap<String, Item>);
 
 impl ApiRequest for Lookup {
     type Reply = LookupResult;
     const ROUTE: &'static str = "api/phid.lookup";
+    const READ_ONLY: bool = true;
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "reqwest"))]
 mod test {
     use super::*;
     use phabricator_mock::PhabMockServer;
@@ -10,13 +10,13 @@ pub use lookup::*;
 #[derive(Deserialize, Debug)]
 pub struct Item {
     #[serde(rename = "fullName")]
-    full_name: String,
-    name: String,
-    phid: Phid,
-    status: String,
-    uri: String,
+    pub full_name: String,
+    pub name: String,
+    pub phid: Phid,
+    pub status: String,
+    pub uri: String,
     #[serde(rename = "type")]
-    ty: String,
+    pub ty: String,
     #[serde(rename = "typeName")]
-    type_name: String,
+    pub type_name: String,
 }
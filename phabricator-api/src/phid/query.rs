@@ -21,9 +21,10 @@ pub enum QueryResult {
 impl ApiRequest for Query {
     type Reply = QueryResult;
     const ROUTE: &'static str = "api/phid.query";
+    const READ_ONLY: bool = true;
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "reqwest"))]
 mod test {
     use phabricator_mock::PhabMockServer;
 
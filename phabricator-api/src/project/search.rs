@@ -78,14 +78,16 @@ pub struct AttachmentsResult {}
 impl ApiRequest for Search {
     type Reply = SearchResult;
     const ROUTE: &'static str = "api/project.search";
+    const READ_ONLY: bool = true;
 }
 
 impl ApiRequest for SearchCursor<'_> {
     type Reply = SearchResult;
     const ROUTE: &'static str = "api/project.search";
+    const READ_ONLY: bool = true;
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "reqwest"))]
 mod test {
     use super::*;
     use phabricator_mock::project::Project;
@@ -0,0 +1,70 @@
+use crate::ApiRequest;
+use serde::{Deserialize, Serialize};
+
+/// Application whose rendering rules are used
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Context {
+    Phriction,
+    Maniphest,
+    Differential,
+    Phame,
+    Feed,
+    Diffusion,
+}
+
+/// Render Remarkup to HTML on the server
+#[derive(Serialize, Debug)]
+pub struct Process {
+    pub context: Context,
+    pub contents: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Rendered {
+    pub content: String,
+}
+
+/// Rendered documents in the same order as the requested contents
+#[derive(Deserialize, Debug)]
+#[serde(transparent)]
+pub struct ProcessResult(pub Vec<Rendered>);
+
+impl ApiRequest for Process {
+    type Reply = ProcessResult;
+    const ROUTE: &'static str = "api/remarkup.process";
+    const READ_ONLY: bool = true;
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use phabricator_mock::PhabMockServer;
+
+    #[tokio::test]
+    async fn simple() {
+        let m = PhabMockServer::start().await;
+        let client = crate::Client::new(m.uri(), m.token().to_string());
+
+        let p = Process {
+            context: Context::Maniphest,
+            contents: vec![
+                "Some **bold** text\n\nSecond <paragraph>".to_string(),
+                "".to_string(),
+                "`code`".to_string(),
+            ],
+        };
+        let r = client.request(&p).await.unwrap();
+
+        assert_eq!(3, r.0.len());
+        assert_eq!(
+            "<p>Some <strong>bold</strong> text</p>\n\n<p>Second &lt;paragraph&gt;</p>",
+            r.0[0].content
+        );
+        assert_eq!("", r.0[1].content);
+        assert_eq!(
+            "<p><tt class=\"remarkup-monospaced\">code</tt></p>",
+            r.0[2].content
+        );
+    }
+}
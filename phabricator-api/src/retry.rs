@@ -0,0 +1,228 @@
+use crate::RequestError;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::Instant;
+
+/// When and how often to retry failed requests. Backoff is exponential with
+/// full jitter: attempt `n` waits a random time up to
+/// `initial_backoff * 2^(n-1)`, capped at `max_backoff`.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    retry_writes: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+            retry_writes: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Attempts in total, including the first one
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Randomize the backoff (the default) so clients failing together
+    /// don't retry together
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Also retry requests that aren't [read only](crate::ApiRequest::READ_ONLY).
+    /// A failed write may still have been applied by the server.
+    pub fn retry_writes(mut self, retry: bool) -> Self {
+        self.retry_writes = retry;
+        self
+    }
+
+    pub(crate) fn should_retry(&self, read_only: bool, attempt: u32, e: &RequestError) -> bool {
+        attempt < self.max_attempts && (read_only || self.retry_writes) && e.is_retryable()
+    }
+
+    /// Time to wait after the given (1-based) failed attempt
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        let backoff = self
+            .initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+        if self.jitter {
+            backoff.mul_f64(random())
+        } else {
+            backoff
+        }
+    }
+}
+
+// Random number in [0, 1); good enough for jitter without pulling in rand
+fn random() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        hasher.write_u128(now.as_nanos());
+    }
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Client side limits on the request rate
+#[derive(Clone, Debug, Default)]
+pub struct RateLimit {
+    per_second: Option<f64>,
+    max_in_flight: Option<usize>,
+}
+
+impl RateLimit {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Maximum number of requests started per second
+    pub fn per_second(mut self, requests: f64) -> Self {
+        self.per_second = Some(requests);
+        self
+    }
+
+    /// Maximum number of requests waiting for a reply at the same time
+    pub fn max_in_flight(mut self, requests: usize) -> Self {
+        self.max_in_flight = Some(requests.max(1));
+        self
+    }
+}
+
+/// Shared state enforcing a [RateLimit] over all clones of a client
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    interval: Option<Duration>,
+    next: Mutex<Option<Instant>>,
+    in_flight: Option<Semaphore>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        RateLimiter {
+            interval: limit
+                .per_second
+                .filter(|r| *r > 0.0)
+                .map(|r| Duration::from_secs_f64(1.0 / r)),
+            next: Mutex::new(None),
+            in_flight: limit.max_in_flight.map(Semaphore::new),
+        }
+    }
+
+    /// Wait until a request may be sent; it counts as in flight until the
+    /// returned permit is dropped
+    pub(crate) async fn acquire(&self) -> Option<SemaphorePermit<'_>> {
+        let permit = match &self.in_flight {
+            Some(s) => Some(s.acquire().await.expect("Semaphore closed")),
+            None => None,
+        };
+
+        if let Some(interval) = self.interval {
+            // Reserve the next free slot, then wait for it
+            let slot = {
+                let mut next = self.next.lock().unwrap();
+                let now = Instant::now();
+                let slot = next.map_or(now, |n| n.max(now));
+                *next = Some(slot + interval);
+                slot
+            };
+            tokio::time::sleep_until(slot).await;
+        }
+        permit
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff() {
+        let policy = RetryPolicy::new()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_secs(1))
+            .jitter(false);
+        let backoffs: Vec<_> = (1..=6).map(|a| policy.backoff(a).as_millis()).collect();
+        assert_eq!(vec![100, 200, 400, 800, 1000, 1000], backoffs);
+        assert_eq!(Duration::from_secs(1), policy.backoff(100));
+
+        let policy = policy.jitter(true);
+        for attempt in 1..10 {
+            assert!(policy.backoff(attempt) <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn classify() {
+        let policy = RetryPolicy::new().max_attempts(3);
+        let api = |code: &str| RequestError::Api {
+            route: "api/test".to_string(),
+            code: code.into(),
+            info: String::new(),
+        };
+        let limited = api("ERR-RATE-LIMIT");
+        let invalid = api("ERR-CONDUIT-CORE");
+        assert!(policy.should_retry(true, 1, &limited));
+        assert!(policy.should_retry(true, 2, &limited));
+        assert!(!policy.should_retry(true, 3, &limited));
+        assert!(!policy.should_retry(false, 1, &limited));
+        assert!(policy
+            .clone()
+            .retry_writes(true)
+            .should_retry(false, 1, &limited));
+        assert!(!policy.should_retry(true, 1, &invalid));
+        let incomplete = RequestError::Incomplete {
+            route: "api/test".to_string(),
+        };
+        assert!(!policy.should_retry(true, 1, &incomplete));
+    }
+
+    #[tokio::test]
+    async fn rate_limit() {
+        let limiter = RateLimiter::new(RateLimit::new().per_second(20.0).max_in_flight(2));
+        let start = Instant::now();
+        for _ in 0..5 {
+            limiter.acquire().await;
+        }
+        // The first request goes out immediately
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        let a = limiter.acquire().await;
+        let _b = limiter.acquire().await;
+        assert_eq!(0, limiter.in_flight.as_ref().unwrap().available_permits());
+        drop(a);
+        assert_eq!(1, limiter.in_flight.as_ref().unwrap().available_permits());
+    }
+}
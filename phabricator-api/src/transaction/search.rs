@@ -0,0 +1,135 @@
+use crate::types::{Cursor, Phid};
+use crate::utils::{deserialize_raw_string, deserialize_timestamp};
+use crate::ApiRequest;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::value::Value as JsonValue;
+
+#[derive(Serialize, Debug, Default)]
+pub struct Constraints {
+    pub phids: Option<Vec<Phid>>,
+    #[serde(rename = "authorPHIDs")]
+    pub authors: Option<Vec<Phid>>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct Search {
+    /// Monogram (e.g. `T123`) or phid of the object
+    #[serde(rename = "objectIdentifier")]
+    pub object: String,
+    pub constraints: Constraints,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SearchCursor<'a> {
+    #[serde(flatten)]
+    pub cursor: &'a Cursor,
+    #[serde(flatten)]
+    pub search: &'a Search,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Comment {
+    pub id: u32,
+    pub phid: Phid,
+    pub version: u32,
+    #[serde(rename = "authorPHID")]
+    pub author: Phid,
+    #[serde(rename = "dateCreated", deserialize_with = "deserialize_timestamp")]
+    pub created: DateTime<Utc>,
+    #[serde(rename = "dateModified", deserialize_with = "deserialize_timestamp")]
+    pub modified: DateTime<Utc>,
+    pub removed: bool,
+    #[serde(deserialize_with = "deserialize_raw_string")]
+    pub content: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Data {
+    pub id: u32,
+    pub phid: Phid,
+    /// Transaction type, `None` for types that aren't exposed over the API
+    #[serde(rename = "type")]
+    pub ty: Option<String>,
+    #[serde(rename = "authorPHID")]
+    pub author: Phid,
+    #[serde(rename = "objectPHID")]
+    pub object: Phid,
+    #[serde(rename = "dateCreated", deserialize_with = "deserialize_timestamp")]
+    pub created: DateTime<Utc>,
+    #[serde(rename = "dateModified", deserialize_with = "deserialize_timestamp")]
+    pub modified: DateTime<Utc>,
+    #[serde(rename = "groupID")]
+    pub group: String,
+    pub comments: Vec<Comment>,
+    pub fields: JsonValue,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SearchResult {
+    pub data: Vec<Data>,
+    pub cursor: Cursor,
+}
+
+impl ApiRequest for Search {
+    type Reply = SearchResult;
+    const ROUTE: &'static str = "api/transaction.search";
+    const READ_ONLY: bool = true;
+}
+
+impl ApiRequest for SearchCursor<'_> {
+    type Reply = SearchResult;
+    const ROUTE: &'static str = "api/transaction.search";
+    const READ_ONLY: bool = true;
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use crate::ErrorCode;
+    use phabricator_mock::PhabMockServer;
+
+    #[tokio::test]
+    async fn comments() {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        let other = m.new_user("other", "Other User");
+        let t = m.new_simple_task(100, &user);
+        t.add_comment(&user, "First");
+        t.add_comment(&other, "Second");
+
+        let client = crate::Client::new(m.uri(), m.token().to_string());
+        let s = Search {
+            object: "T100".to_string(),
+            ..Default::default()
+        };
+        let r = client.request(&s).await.unwrap();
+        let comments: Vec<_> = r
+            .data
+            .iter()
+            .flat_map(|d| d.comments.iter().map(|c| c.content.as_str()))
+            .collect();
+        assert_eq!(vec!["Second", "First"], comments);
+        assert_eq!(Phid(t.phid.to_string()), r.data[0].object);
+
+        let s = Search {
+            object: t.phid.to_string(),
+            constraints: Constraints {
+                authors: Some(vec![Phid(user.phid.to_string())]),
+                ..Default::default()
+            },
+        };
+        let r = client.request(&s).await.unwrap();
+        assert_eq!(1, r.data.len());
+        assert_eq!("First", r.data[0].comments[0].content);
+
+        for object in ["T999", "Tx", "nonsense"] {
+            let s = Search {
+                object: object.to_string(),
+                ..Default::default()
+            };
+            let e = client.request(&s).await.unwrap_err();
+            assert_eq!(Some(&ErrorCode::ConduitCore), e.code());
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::RequestError;
+use async_trait::async_trait;
+use http::header::HeaderMap;
+use std::sync::Arc;
+use url::Url;
+
+/// A single Conduit call as handed to a [Transport]
+#[derive(Debug)]
+pub struct TransportRequest<'a> {
+    /// Full url of the method, i.e. the base url joined with the route
+    pub url: Url,
+    /// Method route, e.g. `api/maniphest.search`
+    pub route: &'a str,
+    /// Form encoded parameters
+    pub form: String,
+    /// Extra headers for this call
+    pub headers: HeaderMap,
+}
+
+/// Sends Conduit calls to the server and returns the raw JSON reply
+#[async_trait]
+pub trait Transport: std::fmt::Debug + Send + Sync {
+    async fn send(&self, request: TransportRequest<'_>) -> Result<Vec<u8>, RequestError>;
+}
+
+// Allows keeping a handle on a transport, e.g. to save a recording
+#[async_trait]
+impl<T: Transport> Transport for Arc<T> {
+    async fn send(&self, request: TransportRequest<'_>) -> Result<Vec<u8>, RequestError> {
+        (**self).send(request).await
+    }
+}
+
+/// The default transport, posting over HTTP(S)
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Debug, Default)]
+pub struct HttpTransport {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest")]
+impl HttpTransport {
+    pub fn new(client: reqwest::Client) -> Self {
+        HttpTransport { client }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+#[async_trait]
+impl Transport for HttpTransport {
+    async fn send(&self, request: TransportRequest<'_>) -> Result<Vec<u8>, RequestError> {
+        let route = request.route;
+        let failed = |source| RequestError::Request {
+            route: route.to_string(),
+            source,
+        };
+        let response = self
+            .client
+            .post(request.url)
+            .headers(request.headers)
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .body(request.form)
+            .send()
+            .await
+            .map_err(failed)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(RequestError::Status {
+                route: route.to_string(),
+                status,
+            });
+        }
+        Ok(response.bytes().await.map_err(failed)?.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::phid::Lookup;
+    use crate::Client;
+
+    // In-process fake answering every call from a fixed reply
+    #[derive(Debug)]
+    struct Fake;
+
+    #[async_trait]
+    impl Transport for Fake {
+        async fn send(&self, request: TransportRequest<'_>) -> Result<Vec<u8>, RequestError> {
+            assert_eq!("http://fake/api/phid.lookup", request.url.as_str());
+            assert_eq!("api.token=secret&names%5B0%5D=T1", request.form);
+            let reply = serde_json::json!({
+                "result": {
+                    "T1": {
+                        "phid": "PHID-TASK-1",
+                        "uri": "http://fake/T1",
+                        "typeName": "Task",
+                        "type": "TASK",
+                        "name": "T1",
+                        "fullName": "T1: Fake",
+                        "status": "open"
+                    }
+                },
+                "error_code": null,
+                "error_info": null
+            });
+            Ok(serde_json::to_vec(&reply).unwrap())
+        }
+    }
+
+    // Answers every call with a null result, like methods without a
+    // return value do
+    #[derive(Debug)]
+    struct Void;
+
+    #[async_trait]
+    impl Transport for Void {
+        async fn send(&self, _request: TransportRequest<'_>) -> Result<Vec<u8>, RequestError> {
+            Ok(br#"{"result":null,"error_code":null,"error_info":null}"#.to_vec())
+        }
+    }
+
+    #[tokio::test]
+    async fn null_result() {
+        let client = Client::with_transport("http://fake/".parse().unwrap(), "secret".into(), Void);
+        let r = client
+            .call_raw("differential.close", serde_json::json!({ "revisionID": 1 }))
+            .await
+            .unwrap();
+        assert!(r.is_null());
+
+        // Typed replies can't be null
+        let l = Lookup {
+            names: vec!["T1".to_string()],
+        };
+        assert!(matches!(
+            client.request(&l).await,
+            Err(RequestError::Incomplete { .. })
+        ));
+
+        for method in ["", "../conduit.query", "maniphest/search"] {
+            assert!(matches!(
+                client.call_raw(method, serde_json::json!({})).await,
+                Err(RequestError::Encode { .. })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn fake() {
+        let client = Client::with_transport("http://fake/".parse().unwrap(), "secret".into(), Fake);
+        let l = Lookup {
+            names: vec!["T1".to_string()],
+        };
+        let r = client.request(&l).await.unwrap();
+        assert_eq!("T1: Fake", r.0["T1"].full_name);
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn connect_failure() {
+        // Nothing listens on the port once the listener is dropped
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let base = format!("http://127.0.0.1:{}/", port).parse().unwrap();
+        let client = Client::new(base, "secret".into());
+        let l = Lookup {
+            names: vec!["T1".to_string()],
+        };
+        let e = client.request(&l).await.unwrap_err();
+        assert!(matches!(&e, RequestError::Request { route, .. } if route == "api/phid.lookup"));
+        assert!(e.is_retryable());
+    }
+}
@@ -2,7 +2,10 @@ use chrono::DateTime;
 use chrono::TimeZone;
 use chrono::Utc;
 use serde::de::Deserializer;
+use serde::ser::Serializer;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::hash::Hash;
 
 pub fn str_or_u32<'de, D>(deserializer: D) -> Result<u32, D::Error>
 where
@@ -23,6 +26,39 @@ where
     Ok(v)
 }
 
+// PHP serializes an empty associative array as [] rather than {}
+pub fn map_or_empty<'de, D, K, V>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+where
+    D: Deserializer<'de>,
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum MorE<K: Eq + Hash, V> {
+        Map(HashMap<K, V>),
+        Empty([(); 0]),
+    }
+
+    let v = match MorE::deserialize(deserializer)? {
+        MorE::Map(m) => m,
+        MorE::Empty(_) => HashMap::new(),
+    };
+    Ok(v)
+}
+
+pub fn deserialize_raw_string<'de, D>(d: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Raw {
+        raw: String,
+    }
+    let r = Raw::deserialize(d)?;
+    Ok(r.raw)
+}
+
 pub fn deserialize_timestamp<'de, D>(d: D) -> Result<DateTime<Utc>, D::Error>
 where
     D: Deserializer<'de>,
@@ -38,3 +74,13 @@ where
     let s = Option::<i64>::deserialize(d)?;
     Ok(s.map(|s| Utc.timestamp(s, 0)))
 }
+
+pub fn serialize_timestamp_option<S>(t: &Option<DateTime<Utc>>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match t {
+        Some(t) => s.serialize_some(&t.timestamp()),
+        None => s.serialize_none(),
+    }
+}
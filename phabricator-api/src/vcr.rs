@@ -0,0 +1,316 @@
+//! Record Conduit sessions to cassette files and replay them offline
+use crate::transport::{Transport, TransportRequest};
+use crate::RequestError;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use thiserror::Error;
+
+const TOKEN_PARAM: &str = "api.token";
+const JSON_PARAM: &str = "params";
+const SCRUBBED: &str = "<scrubbed>";
+
+#[derive(Error, Debug)]
+pub enum VcrError {
+    #[error("Cassette IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid cassette: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// A recorded call and its reply
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub route: String,
+    /// Form parameters with the token scrubbed
+    pub params: Vec<(String, String)>,
+    pub reply: Value,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, VcrError> {
+        let data = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), VcrError> {
+        let data = serde_json::to_vec_pretty(self)?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+}
+
+fn scrubbed_params(form: &str) -> Vec<(String, String)> {
+    url::form_urlencoded::parse(form.as_bytes())
+        .into_owned()
+        .map(|(k, v)| match k.as_str() {
+            TOKEN_PARAM => (k, SCRUBBED.to_string()),
+            JSON_PARAM => {
+                let v = scrub_json(&v).unwrap_or(v);
+                (k, v)
+            }
+            _ => (k, v),
+        })
+        .collect()
+}
+
+// The token is in `__conduit__` with the JSON encoding
+fn scrub_json(params: &str) -> Option<String> {
+    let mut params: Value = serde_json::from_str(params).ok()?;
+    let token = params.get_mut("__conduit__")?.get_mut("token")?;
+    *token = Value::String(SCRUBBED.to_string());
+    Some(params.to_string())
+}
+
+fn transport_error(route: &str, message: String) -> RequestError {
+    RequestError::Transport {
+        route: route.to_string(),
+        source: message.into(),
+    }
+}
+
+/// Transport passing calls on to another transport while recording them
+#[derive(Debug)]
+pub struct Recorder<T> {
+    inner: T,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl<T: Transport> Recorder<T> {
+    /// Record calls made through `inner`, to be saved to `path`
+    pub fn new<P: Into<PathBuf>>(inner: T, path: P) -> Self {
+        Recorder {
+            inner,
+            path: path.into(),
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+
+    /// Write the calls recorded so far
+    pub fn save(&self) -> Result<(), VcrError> {
+        self.cassette().save(&self.path)
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for Recorder<T> {
+    async fn send(&self, request: TransportRequest<'_>) -> Result<Vec<u8>, RequestError> {
+        let route = request.route.to_string();
+        let params = scrubbed_params(&request.form);
+        let reply = self.inner.send(request).await?;
+
+        // Replies that aren't JSON will fail to parse on replay as well
+        let value = serde_json::from_slice(&reply)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&reply).into_owned()));
+        self.cassette
+            .lock()
+            .unwrap()
+            .interactions
+            .push(Interaction {
+                route,
+                params,
+                reply: value,
+            });
+        Ok(reply)
+    }
+}
+
+/// Transport answering calls from a [Cassette]
+///
+/// By default a call is answered by any recorded interaction with the same
+/// route and parameters, preferring ones not replayed yet. In strict mode
+/// calls have to come in the recorded order and each interaction is used
+/// once; anything else is an error.
+#[derive(Debug)]
+pub struct Replayer {
+    interactions: Vec<Interaction>,
+    strict: bool,
+    // Number of times each interaction was replayed
+    played: Mutex<Vec<usize>>,
+}
+
+impl Replayer {
+    pub fn new(cassette: Cassette) -> Self {
+        let played = Mutex::new(vec![0; cassette.interactions.len()]);
+        Replayer {
+            interactions: cassette.interactions,
+            strict: false,
+            played,
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, VcrError> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Recorded interactions that weren't replayed
+    pub fn unplayed(&self) -> Vec<&Interaction> {
+        let played = self.played.lock().unwrap();
+        self.interactions
+            .iter()
+            .zip(played.iter())
+            .filter(|(_, n)| **n == 0)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn find(&self, route: &str, params: &[(String, String)]) -> Result<usize, String> {
+        let played = self.played.lock().unwrap();
+        let matches = |i: &Interaction| i.route == route && i.params == params;
+
+        if self.strict {
+            let next = played.iter().position(|n| *n == 0);
+            return match next.map(|n| (n, &self.interactions[n])) {
+                Some((n, i)) if matches(i) => Ok(n),
+                Some((_, i)) => Err(format!("Expected call to {}", i.route)),
+                None => Err("No more recorded calls".to_string()),
+            };
+        }
+
+        let mut candidates: Vec<usize> = (0..self.interactions.len())
+            .filter(|n| matches(&self.interactions[*n]))
+            .collect();
+        candidates.sort_by_key(|n| played[*n]);
+        candidates
+            .first()
+            .copied()
+            .ok_or_else(|| "No recorded call with these parameters".to_string())
+    }
+}
+
+#[async_trait]
+impl Transport for Replayer {
+    async fn send(&self, request: TransportRequest<'_>) -> Result<Vec<u8>, RequestError> {
+        let params = scrubbed_params(&request.form);
+        let n = self
+            .find(request.route, &params)
+            .map_err(|e| transport_error(request.route, e))?;
+        self.played.lock().unwrap()[n] += 1;
+
+        match &self.interactions[n].reply {
+            Value::String(s) => Ok(s.clone().into_bytes()),
+            v => Ok(serde_json::to_vec(v).expect("Failed to serialize reply")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::phid::Lookup;
+    #[cfg(feature = "reqwest")]
+    use crate::transport::HttpTransport;
+    use crate::Client;
+    #[cfg(feature = "reqwest")]
+    use phabricator_mock::PhabMockServer;
+
+    fn lookup(name: &str) -> Lookup {
+        Lookup {
+            names: vec![name.to_string()],
+        }
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn record_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        m.new_simple_task(1, &user);
+        let recorder = std::sync::Arc::new(Recorder::new(HttpTransport::default(), &path));
+        let client = Client::builder(m.uri(), m.token().to_string())
+            .transport(recorder.clone())
+            .build()
+            .unwrap();
+        let t1 = client.request(&lookup("T1")).await.unwrap();
+        let t2 = client.request(&lookup("T2")).await.unwrap();
+        recorder.save().unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains(m.token()));
+        assert!(saved.contains(SCRUBBED));
+
+        // Replay without the server, with a different token
+        let replayer = std::sync::Arc::new(Replayer::load(&path).unwrap());
+        let client = Client::builder(m.uri(), "other".to_string())
+            .transport(replayer.clone())
+            .build()
+            .unwrap();
+        let r = client.request(&lookup("T2")).await.unwrap();
+        assert_eq!(t2.0.len(), r.0.len());
+        assert_eq!(1, replayer.unplayed().len());
+        let r = client.request(&lookup("T1")).await.unwrap();
+        assert_eq!(t1.0["T1"].phid, r.0["T1"].phid);
+        // Replays can repeat
+        assert!(client.request(&lookup("T1")).await.is_ok());
+        assert!(replayer.unplayed().is_empty());
+        assert!(matches!(
+            client.request(&lookup("T3")).await,
+            Err(RequestError::Transport { .. })
+        ));
+    }
+
+    #[test]
+    fn scrub_json_token() {
+        let form = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair(
+                JSON_PARAM,
+                r#"{"__conduit__":{"token":"secret"},"names":["T1"]}"#,
+            )
+            .append_pair("output", "json")
+            .finish();
+        let params = scrubbed_params(&form);
+        assert!(!params[0].1.contains("secret"));
+        assert!(params[0].1.contains(SCRUBBED));
+        assert!(params[0].1.contains("T1"));
+        assert_eq!(("output".to_string(), "json".to_string()), params[1]);
+    }
+
+    #[tokio::test]
+    async fn strict() {
+        let reply = serde_json::json!({
+            "result": {},
+            "error_code": null,
+            "error_info": null,
+        });
+        let interaction = |name: &str| Interaction {
+            route: "api/phid.lookup".to_string(),
+            params: vec![
+                (TOKEN_PARAM.to_string(), SCRUBBED.to_string()),
+                ("names[0]".to_string(), name.to_string()),
+            ],
+            reply: reply.clone(),
+        };
+        let cassette = Cassette {
+            interactions: vec![interaction("T1"), interaction("T2")],
+        };
+        let client = Client::builder("http://localhost/".parse().unwrap(), "t".to_string())
+            .transport(Replayer::new(cassette.clone()).strict(true))
+            .build()
+            .unwrap();
+        assert!(client.request(&lookup("T2")).await.is_err());
+        assert!(client.request(&lookup("T1")).await.is_ok());
+        assert!(client.request(&lookup("T1")).await.is_err());
+        assert!(client.request(&lookup("T2")).await.is_ok());
+        assert!(client.request(&lookup("T2")).await.is_err());
+    }
+}
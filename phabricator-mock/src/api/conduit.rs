@@ -0,0 +1,114 @@
+use crate::*;
+use serde_json::{json, Map, Value};
+
+const SEARCH: Signature = &[
+    ("queryKey", "optional string"),
+    ("constraints", "optional map<string, wild>"),
+    ("attachments", "optional map<string, bool>"),
+    ("order", "optional order"),
+    ("before", "optional string"),
+    ("after", "optional string"),
+    ("limit", "optional int"),
+];
+
+// Parameter names and types
+type Signature = &'static [(&'static str, &'static str)];
+
+// The methods mocked by the server: name, description, parameters and
+// return type
+const METHODS: &[(&str, &str, Signature, &str)] = &[
+    ("conduit.query", "Returns the parameters of the Conduit methods.", &[], "dict<dict>"),
+    ("maniphest.search", "Read information about tasks.", SEARCH, "map<string, wild>"),
+    (
+        "maniphest.info",
+        "Retrieve information about a Maniphest task, given its ID.",
+        &[("task_id", "required id")],
+        "nonempty dict",
+    ),
+    (
+        "maniphest.edit",
+        "Apply transactions to create a new task or edit an existing one.",
+        &[
+            ("transactions", "list<map<string, wild>>"),
+            ("objectIdentifier", "optional id|phid|string"),
+        ],
+        "map<string, wild>",
+    ),
+    (
+        "phid.lookup",
+        "Look up objects by name.",
+        &[("names", "required list<string>")],
+        "nonempty dict<string, wild>",
+    ),
+    ("project.search", "Read information about projects.", SEARCH, "map<string, wild>"),
+    (
+        "edge.search",
+        "Read edge relationships between objects.",
+        &[
+            ("sourcePHIDs", "list<phid>"),
+            ("types", "list<const>"),
+            ("destinationPHIDs", "optional list<phid>"),
+            ("before", "optional string"),
+            ("after", "optional string"),
+            ("limit", "optional int"),
+        ],
+        "list<dict>",
+    ),
+    (
+        "remarkup.process",
+        "Process text through remarkup.",
+        &[
+            ("context", "required string-constant<'phriction', 'maniphest', 'differential', 'phame', 'feed', 'diffusion'>"),
+            ("contents", "required list<string>"),
+        ],
+        "nonempty dict",
+    ),
+    (
+        "transaction.search",
+        "Read information about transactions.",
+        &[
+            ("objectIdentifier", "phid|string"),
+            ("constraints", "optional map<string, wild>"),
+            ("before", "optional string"),
+            ("after", "optional string"),
+            ("limit", "optional int"),
+        ],
+        "list<dict>",
+    ),
+];
+
+pub struct Query;
+
+impl PhabRespond for Query {
+    fn respond(&self, _server: &PhabMockServer, _params: &Params, _: &Request) -> ResponseTemplate {
+        let methods: Map<String, Value> = METHODS
+            .iter()
+            .map(|(name, description, params, returns)| {
+                // Like PHP, an empty map is encoded as an empty list
+                let params = if params.is_empty() {
+                    json!([])
+                } else {
+                    params
+                        .iter()
+                        .map(|(p, t)| (p.to_string(), json!(t)))
+                        .collect::<Map<_, _>>()
+                        .into()
+                };
+                (
+                    name.to_string(),
+                    json!({
+                        "description": description,
+                        "params": params,
+                        "return": returns,
+                    }),
+                )
+            })
+            .collect();
+
+        ResponseTemplate::new(200).set_body_json(json!({
+            "result": methods,
+            "error_code": null,
+            "error_info": null
+        }))
+    }
+}
@@ -1,4 +1,5 @@
 use crate::*;
+use rust_decimal::Decimal;
 use serde_json::json;
 use serde_json::value::Value::Null;
 
@@ -74,6 +75,12 @@ impl PhabRespond for Search {
         // TODO support query key
         let phids = params.get_values(&["constraints", "phids"]);
         let querykey = params.get_values(&["queryKey"]);
+        let modified_start: Option<u64> = params
+            .get(&["constraints", "modifiedStart"])
+            .map(|v| v.parse().expect("Expected a numeric modifiedStart"));
+        let modified_end: Option<u64> = params
+            .get(&["constraints", "modifiedEnd"])
+            .map(|v| v.parse().expect("Expected a numeric modifiedEnd"));
         let subscribers = self.attachment(params, "subscribers");
         let columns = self.attachment(params, "columns");
         let projects = self.attachment(params, "projects");
@@ -91,6 +98,11 @@ impl PhabRespond for Search {
             })),
         };
 
+        let tasks = tasks.filter(|t| {
+            modified_start.iter().all(|s| t.date_modified >= *s)
+                && modified_end.iter().all(|e| t.date_modified <= *e)
+        });
+
         let responses: Vec<_> = tasks.map(| t | {
                 let mut attachments = HashMap::new();
 
@@ -127,12 +139,7 @@ impl PhabRespond for Search {
                 }
 
                 if projects {
-                    let projects: Vec<_> = t
-                        .columns
-                        .iter()
-                        .map(|c| &c.phid)
-                        .chain(t.projects.iter().map(|p| &p.phid))
-                        .collect();
+                    let projects: Vec<_> = t.projects.iter().map(|p| &p.phid).collect();
                     attachments.insert("projects", json!({ "projectPHIDs": projects }));
                 }
 
@@ -193,3 +200,280 @@ impl PhabRespond for Search {
         }))
     }
 }
+
+enum Op {
+    Add,
+    Remove,
+    Set,
+}
+
+enum Change {
+    Title(String),
+    Description(String),
+    Owner(Option<User>),
+    Status(Status),
+    Priority(Priority),
+    Points(Option<Decimal>),
+    Projects(Op, Vec<Project>),
+    Parents(Op, Vec<Task>),
+    Subtasks(Op, Vec<Task>),
+    Comment(String),
+}
+
+pub struct Edit;
+
+impl Edit {
+    fn error(info: String) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "result": Null,
+            "error_code": "ERR-CONDUIT-CORE",
+            "error_info": info,
+        }))
+    }
+
+    fn find_task(server: &PhabMockServer, identifier: &str) -> Option<Task> {
+        match identifier.strip_prefix('T') {
+            Some(id) => id.parse().ok().and_then(|id| server.get_task(id)),
+            None => match identifier.parse() {
+                Ok(id) => server.get_task(id),
+                Err(_) => identifier
+                    .parse()
+                    .ok()
+                    .and_then(|phid| server.find_task(&phid)),
+            },
+        }
+    }
+
+    fn values<'a>(params: &'a Params, key: &[&str]) -> &'a [String] {
+        params
+            .get_values(key)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    fn change(server: &PhabMockServer, params: &Params, i: usize) -> Result<Change, String> {
+        let i = i.to_string();
+        let ty = params
+            .get(&["transactions", &i, "type"])
+            .ok_or_else(|| format!("Transaction {} has no type", i))?;
+        let value = || {
+            params
+                .get(&["transactions", &i, "value"])
+                .unwrap_or_default()
+        };
+        let tasks = || {
+            Self::values(params, &["transactions", &i, "value"])
+                .iter()
+                .map(|t| Self::find_task(server, t).ok_or_else(|| format!("Unknown task {}", t)))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let projects = || {
+            Self::values(params, &["transactions", &i, "value"])
+                .iter()
+                .map(|p| {
+                    p.parse()
+                        .ok()
+                        .and_then(|phid| server.find_project(&phid))
+                        .ok_or_else(|| format!("Unknown project {}", p))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let change = match ty {
+            "title" => Change::Title(value().to_string()),
+            "description" => Change::Description(value().to_string()),
+            "comment" => Change::Comment(value().to_string()),
+            "owner" => match value() {
+                "" => Change::Owner(None),
+                v => Change::Owner(Some(
+                    v.parse()
+                        .ok()
+                        .and_then(|phid| server.find_user(&phid))
+                        .ok_or_else(|| format!("Unknown user {}", v))?,
+                )),
+            },
+            "status" => Change::Status(
+                server
+                    .find_status(value())
+                    .ok_or_else(|| format!("Unknown status {}", value()))?,
+            ),
+            "priority" => Change::Priority(
+                server
+                    .find_priority(value())
+                    .ok_or_else(|| format!("Unknown priority {}", value()))?,
+            ),
+            "points" => match value() {
+                "" => Change::Points(None),
+                v => Change::Points(Some(
+                    v.parse().map_err(|_| format!("Invalid points {}", v))?,
+                )),
+            },
+            "projects.add" => Change::Projects(Op::Add, projects()?),
+            "projects.remove" => Change::Projects(Op::Remove, projects()?),
+            "projects.set" => Change::Projects(Op::Set, projects()?),
+            "parents.add" => Change::Parents(Op::Add, tasks()?),
+            "parents.remove" => Change::Parents(Op::Remove, tasks()?),
+            "parents.set" => Change::Parents(Op::Set, tasks()?),
+            "subtasks.add" => Change::Subtasks(Op::Add, tasks()?),
+            "subtasks.remove" => Change::Subtasks(Op::Remove, tasks()?),
+            "subtasks.set" => Change::Subtasks(Op::Set, tasks()?),
+            t => return Err(format!("Unsupported transaction type {}", t)),
+        };
+        Ok(change)
+    }
+
+    fn relate(server: &PhabMockServer, id: u32, op: Op, related: Vec<Task>, parents: bool) {
+        let current = || server.get_task(id).unwrap();
+        let pair = |other: &Task| {
+            let other = server.get_task(other.id).unwrap();
+            if parents {
+                (other, current())
+            } else {
+                (current(), other)
+            }
+        };
+
+        let existing = if parents {
+            current().parents()
+        } else {
+            current().subtasks()
+        };
+        let (remove, add) = match op {
+            Op::Add => (vec![], related),
+            Op::Remove => (related, vec![]),
+            Op::Set => (existing.clone(), related),
+        };
+        for t in remove {
+            let (parent, subtask) = pair(&t);
+            task::unlink(&parent, &subtask);
+        }
+        for t in add {
+            let (parent, subtask) = pair(&t);
+            if !parent.subtasks().iter().any(|s| Arc::ptr_eq(s, &subtask)) {
+                task::link(&parent, &subtask);
+            }
+        }
+    }
+}
+
+impl PhabRespond for Edit {
+    fn respond(&self, server: &PhabMockServer, params: &Params, _: &Request) -> ResponseTemplate {
+        let existing = match params.get(&["objectIdentifier"]) {
+            Some(identifier) => match Self::find_task(server, identifier) {
+                Some(t) => Some(t),
+                None => return Self::error(format!("No object \"{}\" exists.", identifier)),
+            },
+            None => None,
+        };
+
+        // Validate everything first, so failing edits don't apply partially
+        let changes = match (0..params.count(&["transactions"]))
+            .map(|i| Self::change(server, params, i))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(changes) => changes,
+            Err(e) => return Self::error(e),
+        };
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let author = server.first_user().expect("No user to act as");
+
+        let task = match existing {
+            Some(t) => t,
+            None => {
+                let title = changes.iter().find_map(|c| match c {
+                    Change::Title(t) => Some(t.clone()),
+                    _ => None,
+                });
+                let title = match title {
+                    Some(title) => title,
+                    None => return Self::error("New tasks must have a title.".to_string()),
+                };
+                let t = crate::task()
+                    .id(server.next_task_id())
+                    .full_name(title)
+                    .description("")
+                    .author(author.clone())
+                    .priority(server.default_priority())
+                    .status(server.default_status())
+                    .date_created(now)
+                    .date_modified(now)
+                    .build()
+                    .unwrap();
+                server.add_task(t.clone());
+                t
+            }
+        };
+
+        // Every applied change gets a transaction, comments get theirs when
+        // they're added
+        let mut transactions: Vec<_> = changes
+            .iter()
+            .filter(|c| !matches!(c, Change::Comment(_)))
+            .map(|_| json!({ "phid": Phid::new_task_transaction() }))
+            .collect();
+        let mut comments = Vec::new();
+        let mut relations = Vec::new();
+        server.update_task(task.id, |t| {
+            t.date_modified = now;
+            for change in changes {
+                match change {
+                    Change::Title(title) => t.full_name = title,
+                    Change::Description(d) => t.description = d,
+                    Change::Owner(owner) => t.owner = owner,
+                    Change::Status(status) => {
+                        if status.closed && !t.status.closed {
+                            t.date_closed = Some(now);
+                            t.closer = Some(author.clone());
+                        } else if !status.closed {
+                            t.date_closed = None;
+                            t.closer = None;
+                        }
+                        t.status = status;
+                    }
+                    Change::Priority(p) => t.priority = p,
+                    Change::Points(p) => t.points = p,
+                    Change::Projects(Op::Add, projects) => {
+                        for p in projects {
+                            if !t.projects.iter().any(|e| e.phid == p.phid) {
+                                t.projects.push(p)
+                            }
+                        }
+                    }
+                    Change::Projects(Op::Remove, projects) => t
+                        .projects
+                        .retain(|e| !projects.iter().any(|p| p.phid == e.phid)),
+                    Change::Projects(Op::Set, projects) => t.projects = projects,
+                    Change::Parents(op, tasks) => relations.push((op, tasks, true)),
+                    Change::Subtasks(op, tasks) => relations.push((op, tasks, false)),
+                    Change::Comment(c) => comments.push(c),
+                }
+            }
+        });
+
+        for (op, tasks, parents) in relations {
+            Self::relate(server, task.id, op, tasks, parents);
+        }
+        let task = server.get_task(task.id).unwrap();
+        transactions.extend(
+            comments
+                .iter()
+                .map(|c| json!({ "phid": task.add_comment(&author, c).transaction_phid })),
+        );
+
+        ResponseTemplate::new(200).set_body_json(json!({
+            "result": {
+                "object": {
+                    "id": task.id,
+                    "phid": task.phid,
+                },
+                "transactions": transactions,
+            },
+            "error_code": Null,
+            "error_info": Null,
+        }))
+    }
+}
@@ -1,4 +1,7 @@
+pub mod conduit;
 pub mod edge;
 pub mod maniphest;
 pub mod phid;
 pub mod project;
+pub mod remarkup;
+pub mod transaction;
@@ -9,22 +9,70 @@ impl PhabRespond for Lookup {
 
         let mut responses = HashMap::new();
         for n in names {
-            if let Some(id) = n.strip_prefix("T") {
-                let id = id.parse().expect("Couldn't parse task");
-                if let Some(t) = server.get_task(id) {
+            if let Some(slug) = n.strip_prefix('#') {
+                if let Some(p) = server.find_project_by_slug(slug) {
                     responses.insert(
                         n,
                         json!({
-                            "phid": t.phid,
-                            "uri": format!("{}/T{}", server.uri(), t.id),
-                            "typeName":"Maniphest Task",
-                            "type":"TASK",
-                            "name": format!("T{}", t.id),
-                            "fullName": t.full_name,
-                            "status":"closed"
+                            "phid": p.phid,
+                            "uri": format!("{}/tag/{}/", server.uri(), slug),
+                            "typeName": "Project",
+                            "type": "PROJ",
+                            "name": p.name,
+                            "fullName": p.name,
+                            "status": "open"
                         }),
                     );
                 }
+                continue;
+            }
+
+            // Users can be looked up by name or phid; names with an @ are
+            // never anything else
+            let user = match n.strip_prefix('@') {
+                Some(name) => Some(server.find_user_by_name(name)),
+                None => n
+                    .parse()
+                    .ok()
+                    .and_then(|phid| server.find_user(&phid))
+                    .map(Some),
+            };
+            if let Some(user) = user {
+                if let Some(u) = user {
+                    responses.insert(
+                        n,
+                        json!({
+                            "phid": u.phid,
+                            "uri": format!("{}/p/{}/", server.uri(), u.name),
+                            "typeName": "User",
+                            "type": "USER",
+                            "name": u.name,
+                            "fullName": format!("{} ({})", u.name, u.full_name),
+                            "status": "open"
+                        }),
+                    );
+                }
+                continue;
+            }
+
+            // Tasks can be looked up by monogram or phid
+            let task = match n.strip_prefix('T') {
+                Some(id) => id.parse().ok().and_then(|id| server.get_task(id)),
+                None => n.parse().ok().and_then(|phid| server.find_task(&phid)),
+            };
+            if let Some(t) = task {
+                responses.insert(
+                    n,
+                    json!({
+                        "phid": t.phid,
+                        "uri": format!("{}/T{}", server.uri(), t.id),
+                        "typeName":"Maniphest Task",
+                        "type":"TASK",
+                        "name": format!("T{}", t.id),
+                        "fullName": t.full_name,
+                        "status": if t.status.closed { "closed" } else { "open" }
+                    }),
+                );
             }
         }
 
@@ -0,0 +1,88 @@
+use crate::*;
+use serde_json::json;
+
+const CONTEXTS: &[&str] = &[
+    "phriction",
+    "maniphest",
+    "differential",
+    "phame",
+    "feed",
+    "diffusion",
+];
+
+pub struct Process;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Replace pairs of `marker` with the given tags
+fn style(text: &str, marker: &str, open: &str, close: &str) -> String {
+    let parts: Vec<&str> = text.split(marker).collect();
+    if parts.len() < 3 {
+        return text.to_string();
+    }
+
+    let mut out = String::new();
+    let pairs = (parts.len() - 1) / 2;
+    for (i, part) in parts.iter().enumerate() {
+        if i > 0 {
+            if i > pairs * 2 {
+                out.push_str(marker);
+            } else if i % 2 == 1 {
+                out.push_str(open);
+            } else {
+                out.push_str(close);
+            }
+        }
+        out.push_str(part);
+    }
+    out
+}
+
+// Much simplified version of Phabricator's renderer: paragraphs, line
+// breaks and a few inline styles only
+fn render(content: &str) -> String {
+    content
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let p = escape(p);
+            let p = style(&p, "`", "<tt class=\"remarkup-monospaced\">", "</tt>");
+            let p = style(&p, "**", "<strong>", "</strong>");
+            let p = style(&p, "//", "<em>", "</em>");
+            format!("<p>{}</p>", p.replace('\n', "<br />\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+impl PhabRespond for Process {
+    fn respond(&self, _server: &PhabMockServer, params: &Params, _: &Request) -> ResponseTemplate {
+        let context = params.get(&["context"]).expect("Expected context");
+        if !CONTEXTS.contains(&context) {
+            return ResponseTemplate::new(200).set_body_json(json!({
+                "result": null,
+                "error_code": "ERR-INVALID-ENGINE",
+                "error_info": format!("Unknown context \"{}\".", context),
+            }));
+        }
+
+        let rendered: Vec<_> = params
+            .get_values(&["contents"])
+            .into_iter()
+            .flatten()
+            .map(|c| json!({ "content": render(c) }))
+            .collect();
+
+        ResponseTemplate::new(200).set_body_json(json!({
+            "result": rendered,
+            "error_code": null,
+            "error_info": null
+        }))
+    }
+}
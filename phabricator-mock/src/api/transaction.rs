@@ -0,0 +1,83 @@
+use crate::*;
+use serde_json::json;
+
+pub struct Search;
+
+impl Search {
+    fn error(info: String) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "result": null,
+            "error_code": "ERR-CONDUIT-CORE",
+            "error_info": info,
+        }))
+    }
+}
+
+impl PhabRespond for Search {
+    fn respond(&self, server: &PhabMockServer, params: &Params, _: &Request) -> ResponseTemplate {
+        let object = match params.get(&["objectIdentifier"]) {
+            Some(object) => object,
+            None => return Self::error("An object identifier is required.".to_string()),
+        };
+        let authors = params.get_values(&["constraints", "authorPHIDs"]);
+
+        let task = match object.strip_prefix('T') {
+            Some(id) => id.parse().ok().and_then(|id| server.get_task(id)),
+            None => object.parse().ok().and_then(|phid| server.find_task(&phid)),
+        };
+        let task = match task {
+            Some(task) => task,
+            None => return Self::error(format!("No object \"{}\" exists.", object)),
+        };
+
+        // Only comments are tracked, returned newest first like phabricator
+        let responses: Vec<_> = task
+            .comments()
+            .iter()
+            .rev()
+            .filter(|c| {
+                authors
+                    .iter()
+                    .all(|a| a.iter().any(|a| *a == c.author.phid.to_string()))
+            })
+            .map(|c| {
+                json!({
+                    "id": c.transaction_id,
+                    "phid": c.transaction_phid,
+                    "type": "comment",
+                    "authorPHID": c.author.phid,
+                    "objectPHID": task.phid,
+                    "dateCreated": c.date_created,
+                    "dateModified": c.date_created,
+                    "groupID": format!("group{}", c.transaction_id),
+                    "comments": [{
+                        "id": c.id,
+                        "phid": c.phid,
+                        "version": 1,
+                        "authorPHID": c.author.phid,
+                        "dateCreated": c.date_created,
+                        "dateModified": c.date_created,
+                        "removed": false,
+                        "content": { "raw": c.content }
+                    }],
+                    "fields": {}
+                })
+            })
+            .collect();
+
+        // TODO handle cursor
+        ResponseTemplate::new(200).set_body_json(json!({
+            "result":  {
+                "data": responses,
+                "cursor": {
+                    "limit": 100,
+                    "after": null,
+                    "before": null,
+                    "order": null,
+                },
+            },
+            "error_code":null,
+            "error_info":null
+        }))
+    }
+}
@@ -79,8 +79,12 @@ where
     R: PhabRespond,
 {
     fn respond(&self, request: &Request) -> ResponseTemplate {
+        if let Some(status) = self.server.take_failure() {
+            return ResponseTemplate::new(status).set_body_string("Injected failure");
+        }
+
         let params = Params::new(&request.body).expect("Failed to parse request");
-        let auth = params.get(&["api.token"]);
+        let auth = params.token();
 
         match auth {
             None => ResponseTemplate::new(403).set_body_string("Missing auth token"),
@@ -105,6 +109,8 @@ struct Inner {
     server: MockServer,
     token: String,
     data: Mutex<Data>,
+    // Number of upcoming requests to fail and the HTTP status to fail with
+    failures: Mutex<(usize, u16)>,
 }
 
 #[derive(Clone)]
@@ -158,6 +164,7 @@ impl PhabMockServer {
                 server,
                 token: "badgerbadger".to_string(),
                 data: Mutex::new(data),
+                failures: Mutex::new((0, 500)),
             }),
         };
 
@@ -183,14 +190,22 @@ impl PhabMockServer {
             .unwrap();
         m.add_status(s);
 
+        m.handle_post("api/conduit.query", api::conduit::Query {})
+            .await;
         m.handle_post("api/maniphest.search", api::maniphest::Search {})
             .await;
         m.handle_post("api/maniphest.info", api::maniphest::Info {})
             .await;
+        m.handle_post("api/maniphest.edit", api::maniphest::Edit {})
+            .await;
         m.handle_post("api/phid.lookup", api::phid::Lookup {}).await;
         m.handle_post("api/project.search", api::project::Search {})
             .await;
         m.handle_post("api/edge.search", api::edge::Search {}).await;
+        m.handle_post("api/remarkup.process", api::remarkup::Process {})
+            .await;
+        m.handle_post("api/transaction.search", api::transaction::Search {})
+            .await;
         m
     }
 
@@ -215,6 +230,20 @@ impl PhabMockServer {
         self.inner.server.received_requests().await
     }
 
+    /// Fail the next `n` requests with the given HTTP status
+    pub fn fail_requests(&self, n: usize, status: u16) {
+        *self.inner.failures.lock().unwrap() = (n, status);
+    }
+
+    fn take_failure(&self) -> Option<u16> {
+        let mut failures = self.inner.failures.lock().unwrap();
+        if failures.0 == 0 {
+            return None;
+        }
+        failures.0 -= 1;
+        Some(failures.1)
+    }
+
     pub fn add_task(&self, task: Task) {
         let mut data = self.inner.data.lock().unwrap();
         data.tasks.insert(task.id, task);
@@ -256,6 +285,38 @@ impl PhabMockServer {
             .map(Clone::clone)
     }
 
+    /// Replace the task with an edited copy, see [`task::replace`]
+    pub fn update_task<F>(&self, id: u32, f: F) -> Option<Task>
+    where
+        F: FnOnce(&mut task::TaskData),
+    {
+        let mut data = self.inner.data.lock().unwrap();
+        let old = data.tasks.get(&id)?;
+        let new = task::replace(old, f);
+        data.tasks.insert(id, new.clone());
+        Some(new)
+    }
+
+    pub fn next_task_id(&self) -> u32 {
+        let data = self.inner.data.lock().unwrap();
+        data.tasks.keys().max().map_or(1, |id| id + 1)
+    }
+
+    pub fn first_user(&self) -> Option<User> {
+        let data = self.inner.data.lock().unwrap();
+        data.users.first().cloned()
+    }
+
+    pub fn find_user(&self, phid: &Phid) -> Option<User> {
+        let data = self.inner.data.lock().unwrap();
+        data.users.iter().find(|u| u.phid == *phid).cloned()
+    }
+
+    pub fn find_user_by_name(&self, name: &str) -> Option<User> {
+        let data = self.inner.data.lock().unwrap();
+        data.users.iter().find(|u| u.name == name).cloned()
+    }
+
     pub fn get_project(&self, id: u32) -> Option<Project> {
         let data = self.inner.data.lock().unwrap();
         data.projects.iter().find(|p| p.id == id).map(Clone::clone)
@@ -269,6 +330,28 @@ impl PhabMockServer {
             .map(Clone::clone)
     }
 
+    pub fn find_project_by_slug(&self, slug: &str) -> Option<Project> {
+        let data = self.inner.data.lock().unwrap();
+        data.projects
+            .iter()
+            .find(|p| p.slug.as_deref() == Some(slug))
+            .cloned()
+    }
+
+    pub fn find_status(&self, value: &str) -> Option<Status> {
+        let data = self.inner.data.lock().unwrap();
+        data.statusses.iter().find(|s| s.value == value).cloned()
+    }
+
+    pub fn find_priority(&self, name: &str) -> Option<Priority> {
+        let data = self.inner.data.lock().unwrap();
+        data.priorities
+            .iter()
+            .chain(std::iter::once(&data.default_priority))
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
     pub fn default_status(&self) -> Status {
         let data = self.inner.data.lock().unwrap();
         data.statusses
@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 //use anyhow::Result;
 use anyhow::{anyhow, bail, ensure, Result};
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
 #[allow(dead_code)]
@@ -18,7 +19,7 @@ impl Params {
     fn insert_key_value(&mut self, key: &str, value: &str) -> Result<()> {
         let mut m = &mut self.0;
 
-        let mut parts = key
+        let parts = key
             .split('[')
             .enumerate()
             .map(|(i, v)| {
@@ -29,45 +30,53 @@ impl Params {
                     Ok(v)
                 }
             })
-            .peekable();
-        while let Some(p) = parts.next().transpose()? {
-            if let Some(next) = parts.peek() {
-                let next = match next {
-                    Ok(ref n) => n,
-                    Err(_) => parts.next().unwrap()?,
-                };
-                if let Ok(index) = next.parse::<usize>() {
-                    match m
-                        .entry(p.to_string())
-                        .or_insert_with(|| Param::Values(Vec::new()))
-                    {
-                        Param::Values(ref mut values) => {
-                            ensure!(parts.count() == 1, "index parameter not last");
-                            ensure!(values.len() == index, "parameter index not in order");
-                            values.push(value.to_string());
-                            return Ok(());
-                        }
-                        _ => bail!("Inconsistent parameter hierarchy"),
-                    }
-                } else {
-                    m = match m
-                        .entry(p.to_string())
-                        .or_insert_with(|| Param::Key(HashMap::new()))
-                    {
-                        Param::Key(ref mut m) => m,
-                        _ => bail!("Invalid parameters"),
-                    };
-                }
-            } else if m
-                .insert(p.to_string(), Param::Value(value.to_string()))
-                .is_some()
+            .collect::<Result<Vec<_>>>()?;
+
+        // A trailing index adds to a list of values, other indexes (e.g.
+        // in lists of structures) are treated like normal keys
+        let (path, index) = match parts.split_last() {
+            Some((last, path)) if !path.is_empty() => match last.parse::<usize>() {
+                Ok(index) => (path, Some(index)),
+                Err(_) => (parts.as_slice(), None),
+            },
+            _ => (parts.as_slice(), None),
+        };
+        let (name, path) = path.split_last().ok_or_else(|| anyhow!("Empty key"))?;
+
+        for p in path {
+            m = match m
+                .entry(p.to_string())
+                .or_insert_with(|| Param::Key(HashMap::new()))
             {
-                bail!("Duplicate key in parameters");
+                Param::Key(ref mut m) => m,
+                _ => bail!("Inconsistent parameter hierarchy"),
+            };
+        }
+
+        match index {
+            Some(index) => match m
+                .entry(name.to_string())
+                .or_insert_with(|| Param::Values(Vec::new()))
+            {
+                Param::Values(ref mut values) => {
+                    ensure!(values.len() == index, "parameter index not in order");
+                    values.push(value.to_string());
+                }
+                _ => bail!("Inconsistent parameter hierarchy"),
+            },
+            None => {
+                if m.insert(name.to_string(), Param::Value(value.to_string()))
+                    .is_some()
+                {
+                    bail!("Duplicate key in parameters");
+                }
             }
         }
         Ok(())
     }
 
+    /// Parse either nested form fields or a JSON document in the `params`
+    /// field
     pub fn new(input: &[u8]) -> Result<Self> {
         let parser = form_urlencoded::parse(input);
         let mut this = Params(HashMap::new());
@@ -75,9 +84,21 @@ impl Params {
         for (key, value) in parser {
             this.insert_key_value(&key, &value)?;
         }
+
+        if let Some(Param::Value(json)) = this.0.get("params") {
+            if let Ok(Value::Object(m)) = serde_json::from_str(json) {
+                return Ok(Params(from_json_map(m)));
+            }
+        }
         Ok(this)
     }
 
+    /// The API token, from either encoding
+    pub fn token(&self) -> Option<&str> {
+        self.get(&["api.token"])
+            .or_else(|| self.get(&["__conduit__", "token"]))
+    }
+
     fn do_get(&self, key: &[&str]) -> Option<&Param> {
         let mut p: &Param = self.0.get(key[0])?;
 
@@ -104,6 +125,49 @@ impl Params {
             _ => None,
         }
     }
+
+    /// Number of entries in a list, either of values or of structures
+    pub fn count(&self, key: &[&str]) -> usize {
+        match self.do_get(key) {
+            Some(Param::Values(v)) => v.len(),
+            Some(Param::Key(m)) => (0..)
+                .take_while(|i: &usize| m.contains_key(&i.to_string()))
+                .count(),
+            _ => 0,
+        }
+    }
+}
+
+// Convert to the same structure as the form encoding gives; nulls are left
+// out like unset options are
+fn from_json_map(m: Map<String, Value>) -> HashMap<String, Param> {
+    m.into_iter()
+        .filter_map(|(k, v)| from_json(v).map(|p| (k, p)))
+        .collect()
+}
+
+fn from_json(v: Value) -> Option<Param> {
+    match v {
+        Value::Object(m) => Some(Param::Key(from_json_map(m))),
+        Value::Array(a) if a.iter().all(|v| !v.is_object() && !v.is_array()) => {
+            Some(Param::Values(a.into_iter().filter_map(scalar).collect()))
+        }
+        Value::Array(a) => Some(Param::Key(
+            a.into_iter()
+                .enumerate()
+                .filter_map(|(i, v)| from_json(v).map(|p| (i.to_string(), p)))
+                .collect(),
+        )),
+        v => scalar(v).map(Param::Value),
+    }
+}
+
+fn scalar(v: Value) -> Option<String> {
+    match v {
+        Value::Null => None,
+        Value::String(s) => Some(s),
+        v => Some(v.to_string()),
+    }
 }
 
 #[cfg(test)]
@@ -138,6 +202,53 @@ mod tests {
         assert_eq!(["value0", "value1", "value2"], values.as_slice());
     }
 
+    #[test]
+    fn array_of_keys() {
+        let p = Params::new(
+            b"t[0][type]=title&t[0][value]=Title&t[1][type]=projects&t[1][value][0]=a&t[1][value][1]=b",
+        )
+        .expect("Failed to create params");
+        assert_eq!(2, p.count(&["t"]));
+        assert_eq!(Some("title"), p.get(&["t", "0", "type"]));
+        assert_eq!(Some("Title"), p.get(&["t", "0", "value"]));
+        let values = p.get_values(&["t", "1", "value"]).expect("Missing key");
+        assert_eq!(["a", "b"], values.as_slice());
+    }
+
+    #[test]
+    fn json() {
+        let json = r#"{
+            "__conduit__": { "token": "secret" },
+            "key": "value",
+            "ids": [1, 2],
+            "none": null,
+            "attachments": { "projects": true },
+            "t": [
+                { "type": "title", "value": "Title" },
+                { "type": "projects", "value": ["a", "b"] }
+            ]
+        }"#;
+        let body: String = form_urlencoded::Serializer::new(String::new())
+            .append_pair("params", json)
+            .append_pair("output", "json")
+            .append_pair("__conduit__", "1")
+            .finish();
+        let p = Params::new(body.as_bytes()).expect("Failed to create params");
+        assert_eq!(Some("secret"), p.token());
+        assert_eq!(Some("value"), p.get(&["key"]));
+        assert_eq!(None, p.get(&["none"]));
+        assert_eq!(Some("true"), p.get(&["attachments", "projects"]));
+        let ids = p.get_values(&["ids"]).expect("Missing key");
+        assert_eq!(["1", "2"], ids.as_slice());
+        assert_eq!(2, p.count(&["t"]));
+        assert_eq!(Some("Title"), p.get(&["t", "0", "value"]));
+        let values = p.get_values(&["t", "1", "value"]).expect("Missing key");
+        assert_eq!(["a", "b"], values.as_slice());
+
+        let p = Params::new(b"api.token=secret").expect("Failed to create params");
+        assert_eq!(Some("secret"), p.token());
+    }
+
     #[test]
     fn duplicate_value() {
         let p = Params::new(b"key=value0&key=value1");
@@ -9,6 +9,8 @@ pub enum PhidType {
     Column,
     Project,
     Task,
+    TaskTransaction,
+    TaskComment,
     User,
 }
 
@@ -18,6 +20,8 @@ impl fmt::Display for PhidType {
             PhidType::Column => "PCOL",
             PhidType::Project => "PROJ",
             PhidType::Task => "TASK",
+            PhidType::TaskTransaction => "XACT-TASK",
+            PhidType::TaskComment => "XCMT-TASK",
             PhidType::User => "USER",
         };
         write!(f, "{}", t)
@@ -80,6 +84,14 @@ impl Phid {
     pub fn new_column() -> Self {
         Self::new(PhidType::Column)
     }
+
+    pub fn new_task_transaction() -> Self {
+        Self::new(PhidType::TaskTransaction)
+    }
+
+    pub fn new_task_comment() -> Self {
+        Self::new(PhidType::TaskComment)
+    }
 }
 
 impl fmt::Display for Phid {
@@ -92,15 +104,22 @@ impl std::str::FromStr for Phid {
     type Err = (); //TODO error handling
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let s = s.strip_prefix("PHID-").ok_or(())?;
-        let mut split = s.splitn(2, '-');
-        let ty = match split.next().ok_or(())? {
-            "PCOL" => PhidType::Column,
-            "PROJ" => PhidType::Project,
-            "TASK" => PhidType::Task,
-            "USER" => PhidType::User,
-            _ => return Err(()),
+        let (ty, id) = if let Some(id) = s.strip_prefix("XACT-TASK-") {
+            (PhidType::TaskTransaction, id)
+        } else if let Some(id) = s.strip_prefix("XCMT-TASK-") {
+            (PhidType::TaskComment, id)
+        } else {
+            let mut split = s.splitn(2, '-');
+            let ty = match split.next().ok_or(())? {
+                "PCOL" => PhidType::Column,
+                "PROJ" => PhidType::Project,
+                "TASK" => PhidType::Task,
+                "USER" => PhidType::User,
+                _ => return Err(()),
+            };
+            (ty, split.next().ok_or(())?)
         };
-        let id = split.next().ok_or(())?.to_string();
+        let id = id.to_string();
 
         Ok(Phid { ty, id })
     }
@@ -60,6 +60,19 @@ pub struct TaskData {
     parents: Mutex<Vec<Weak<TaskData>>>,
     #[builder(default)]
     subtasks: Mutex<Vec<Task>>,
+    #[builder(default)]
+    comments: Mutex<Vec<Comment>>,
+}
+
+#[derive(Clone)]
+pub struct Comment {
+    pub id: u32,
+    pub phid: Phid,
+    pub transaction_id: u32,
+    pub transaction_phid: Phid,
+    pub author: User,
+    pub content: String,
+    pub date_created: u64,
 }
 
 impl TaskDataBuilder {
@@ -78,6 +91,85 @@ impl TaskData {
         let subtasks = self.subtasks.lock().unwrap();
         subtasks.clone()
     }
+
+    pub fn comments(&self) -> Vec<Comment> {
+        let comments = self.comments.lock().unwrap();
+        comments.clone()
+    }
+
+    pub fn add_comment(&self, author: &User, content: &str) -> Comment {
+        let mut comments = self.comments.lock().unwrap();
+        let id = comments.len() as u32 + 1;
+        let comment = Comment {
+            id,
+            phid: Phid::new_task_comment(),
+            transaction_id: id,
+            transaction_phid: Phid::new_task_transaction(),
+            author: author.clone(),
+            content: content.to_string(),
+            date_created: self.date_modified + id as u64,
+        };
+        comments.push(comment.clone());
+        comment
+    }
+}
+
+/// Replace `task` by an edited copy, moving its relations and comments over
+/// to the copy. Existing references to `task` keep seeing the old data.
+pub fn replace<F>(task: &Task, f: F) -> Task
+where
+    F: FnOnce(&mut TaskData),
+{
+    let mut data = TaskData {
+        id: task.id,
+        full_name: task.full_name.clone(),
+        phid: task.phid.clone(),
+        description: task.description.clone(),
+        author: task.author.clone(),
+        owner: task.owner.clone(),
+        priority: task.priority.clone(),
+        points: task.points,
+        closer: task.closer.clone(),
+        status: task.status.clone(),
+        date_created: task.date_created,
+        date_modified: task.date_modified,
+        date_closed: task.date_closed,
+        space: task.space.clone(),
+        policy: task.policy.clone(),
+        projects: task.projects.clone(),
+        columns: task.columns.clone(),
+        subscribers: task.subscribers.clone(),
+        parents: Mutex::new(task.parents.lock().unwrap().clone()),
+        subtasks: Mutex::new(task.subtasks()),
+        comments: Mutex::new(task.comments()),
+    };
+    f(&mut data);
+    let new = Arc::new(data);
+
+    for parent in new.parents() {
+        let mut subtasks = parent.subtasks.lock().unwrap();
+        for s in subtasks.iter_mut().filter(|s| Arc::ptr_eq(s, task)) {
+            *s = new.clone();
+        }
+    }
+    for subtask in new.subtasks() {
+        let mut parents = subtask.parents.lock().unwrap();
+        for p in parents
+            .iter_mut()
+            .filter(|p| p.ptr_eq(&Arc::downgrade(task)))
+        {
+            *p = Arc::downgrade(&new);
+        }
+    }
+    new
+}
+
+pub fn unlink(parent: &Task, subtask: &Task) {
+    let mut p = parent.subtasks.lock().unwrap();
+    p.retain(|s| !Arc::ptr_eq(s, subtask));
+
+    let mut s = subtask.parents.lock().unwrap();
+    s.retain(|p| !p.ptr_eq(&Arc::downgrade(parent)));
 }
 
 pub fn link(parent: &Task, subtask: &Task) {
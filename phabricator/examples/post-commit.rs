@@ -0,0 +1,28 @@
+//! Post-commit hook mentioning the commit on referenced tasks
+//!
+//! Install as `.git/hooks/post-commit` wrapper; the server and token are
+//! taken from the arc configuration. Set `PHABRICATOR_CLOSE` to also close
+//! tasks referenced with a closing verb like `Fixes T123`.
+use anyhow::Result;
+use phabricator::{Client, Config};
+use std::process::Command;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+    let close = std::env::var_os("PHABRICATOR_CLOSE").is_some();
+
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%H%n%B"])
+        .output()?;
+    let log = String::from_utf8(output.stdout)?;
+    let (hash, message) = log.split_once('\n').unwrap_or((&log, ""));
+
+    let client = Client::from_config(&Config::load()?);
+    let commit = client.resolve_commit(message).await?;
+    let comment = format!("Mentioned in commit {}", hash);
+    for task in commit.apply(&client, &comment, close).await? {
+        println!("Closed T{} as {}", task.id(), task.status());
+    }
+    Ok(())
+}
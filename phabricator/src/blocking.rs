@@ -0,0 +1,170 @@
+//! Synchronous versions of the high-level queries
+//!
+//! Like [phabricator_api::blocking::Client] these run on a runtime of their
+//! own, so they mustn't be used from within an async context.
+use crate::{Project, Task};
+use futures::prelude::*;
+use phabricator_api::types::Phid;
+use phabricator_api::RequestError;
+use std::io;
+#[cfg(feature = "reqwest")]
+use url::Url;
+
+#[derive(Clone, Debug)]
+pub struct Client {
+    client: crate::Client,
+    api: phabricator_api::blocking::Client,
+}
+
+impl Client {
+    #[cfg(feature = "reqwest")]
+    pub fn new(base: Url, token: String) -> io::Result<Self> {
+        Self::try_from_async(crate::Client::new(base, token))
+    }
+
+    /// Wrap an async client, sharing its cache. Fails if the runtime can't
+    /// be started.
+    pub fn try_from_async(client: crate::Client) -> io::Result<Self> {
+        let api = phabricator_api::blocking::Client::try_from_async(client.client().clone())?;
+        Ok(Client { client, api })
+    }
+
+    /// The wrapped async client, sharing the cache with this one
+    pub fn as_async(&self) -> &crate::Client {
+        &self.client
+    }
+
+    /// Blocking low-level client, for requests not covered here
+    pub fn api(&self) -> &phabricator_api::blocking::Client {
+        &self.api
+    }
+
+    pub fn tasks<'a, T>(&self, tasks: T) -> TasksBuilder<'_>
+    where
+        T: IntoIterator<Item = &'a u32> + 'a,
+    {
+        TasksBuilder {
+            api: &self.api,
+            builder: self.client.tasks(tasks),
+        }
+    }
+
+    pub fn tasks_by_phid<'a, T>(&'a self, phids: &'a mut T) -> TasksBuilder<'a>
+    where
+        T: Iterator<Item = &'a Phid>,
+    {
+        TasksBuilder {
+            api: &self.api,
+            builder: self.client.tasks_by_phid(phids),
+        }
+    }
+
+    pub fn projects_by_phid<'a, P>(&self, phids: P) -> ProjectsBuilder<'_, P>
+    where
+        P: IntoIterator<Item = &'a Phid>,
+    {
+        ProjectsBuilder {
+            api: &self.api,
+            builder: self.client.projects_by_phid(phids),
+        }
+    }
+}
+
+/// Blocking version of [crate::tasksbuilder::TasksBuilder]
+pub struct TasksBuilder<'c> {
+    api: &'c phabricator_api::blocking::Client,
+    builder: crate::TasksBuilder<'c>,
+}
+
+impl<'c> TasksBuilder<'c> {
+    pub fn projects(mut self) -> Self {
+        self.builder = self.builder.projects();
+        self
+    }
+
+    pub fn columns(mut self) -> Self {
+        self.builder = self.builder.columns();
+        self
+    }
+
+    /// Always retrieve the tasks from the server and don't store them in
+    /// the cache
+    pub fn uncached(mut self) -> Self {
+        self.builder = self.builder.uncached();
+        self
+    }
+
+    pub fn query(self) -> Result<Vec<Task>, RequestError> {
+        self.api.block_on(self.builder.query().try_collect())
+    }
+}
+
+/// Blocking version of [crate::projectsbuilder::ProjectsBuilder]
+pub struct ProjectsBuilder<'c, P> {
+    api: &'c phabricator_api::blocking::Client,
+    builder: crate::ProjectsBuilder<'c, P>,
+}
+
+impl<'a, 'c, P> ProjectsBuilder<'c, P>
+where
+    P: IntoIterator<Item = &'a Phid>,
+{
+    /// Always retrieve the projects from the server and don't store them in
+    /// the cache
+    pub fn uncached(mut self) -> Self {
+        self.builder = self.builder.uncached();
+        self
+    }
+
+    pub fn query(self) -> Result<Vec<Project>, RequestError> {
+        self.api.block_on(self.builder.query().try_collect())
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use phabricator_mock::PhabMockServer;
+
+    #[test]
+    fn query() {
+        // The mock server needs a runtime of its own to keep serving
+        let server = tokio::runtime::Runtime::new().unwrap();
+        let m = server.block_on(PhabMockServer::start());
+        let user = m.new_user("user", "Test User");
+        let project = phabricator_mock::project()
+            .id(10)
+            .name("Project")
+            .build()
+            .unwrap();
+        m.add_project(project.clone());
+        let task = phabricator_mock::task()
+            .id(100)
+            .full_name("Blocking")
+            .description("")
+            .author(user.clone())
+            .owner(user)
+            .priority(m.default_priority())
+            .status(m.default_status())
+            .projects(vec![project])
+            .build()
+            .unwrap();
+        m.add_task(task);
+
+        let client = Client::new(m.uri(), m.token().to_string()).unwrap();
+        let tasks = client.tasks(&[100]).projects().query().unwrap();
+        assert_eq!(1, tasks.len());
+        assert_eq!("Blocking", tasks[0].title());
+        assert!(client.as_async().cached_task(100).is_some());
+        let projects = client.api().block_on(tasks[0].projects()).unwrap();
+        assert_eq!(10, projects[0].id());
+
+        let projects = client
+            .projects_by_phid(&[projects[0].phid().clone()])
+            .uncached()
+            .query()
+            .unwrap();
+        assert_eq!(1, projects.len());
+        assert_eq!("Project", projects[0].title());
+    }
+}
@@ -0,0 +1,911 @@
+use crate::{Client, Task};
+use futures::prelude::*;
+use phabricator_api::maniphest::edit::{Edit, Transaction};
+use phabricator_api::types::Phid;
+use phabricator_api::RequestError;
+use rust_decimal::Decimal;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "serde")]
+use std::io::{BufRead, Write};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum UndoLogError {
+    #[error("Undo log IO failure: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Undo log (de)serialization failure: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Change to apply to every task of a bulk edit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BulkChange {
+    Status(String),
+    Owner(Option<Phid>),
+    /// Change the owner only of tasks owned by `from`
+    Reassign {
+        from: Option<Phid>,
+        to: Option<Phid>,
+    },
+    AddProject(Phid),
+    RemoveProject(Phid),
+    /// Replace `from` by `to` only on tasks tagged with `from`
+    MoveProject {
+        from: Phid,
+        to: Phid,
+    },
+    Points(Option<Decimal>),
+    /// Comment on the tasks that get changed otherwise
+    Comment(String),
+}
+
+/// Change of a single task field, from the old to the new value
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(tag = "field", rename_all = "lowercase")
+)]
+pub enum FieldChange {
+    Status {
+        old: String,
+        new: String,
+    },
+    Owner {
+        old: Option<Phid>,
+        new: Option<Phid>,
+    },
+    Projects {
+        added: Vec<Phid>,
+        removed: Vec<Phid>,
+    },
+    Points {
+        old: Option<Decimal>,
+        new: Option<Decimal>,
+    },
+    Comment {
+        text: String,
+    },
+}
+
+impl FieldChange {
+    /// Change reverting this one; comments can't be reverted
+    pub fn inverse(&self) -> Option<FieldChange> {
+        let inverse = match self.clone() {
+            FieldChange::Status { old, new } => FieldChange::Status { old: new, new: old },
+            FieldChange::Owner { old, new } => FieldChange::Owner { old: new, new: old },
+            FieldChange::Projects { added, removed } => FieldChange::Projects {
+                added: removed,
+                removed: added,
+            },
+            FieldChange::Points { old, new } => FieldChange::Points { old: new, new: old },
+            FieldChange::Comment { .. } => return None,
+        };
+        Some(inverse)
+    }
+
+    // Combine with a change of the same field made after this one
+    fn merge(&mut self, next: &FieldChange) -> bool {
+        match (self, next) {
+            (FieldChange::Status { new, .. }, FieldChange::Status { new: next, .. }) => {
+                *new = next.clone()
+            }
+            (FieldChange::Owner { new, .. }, FieldChange::Owner { new: next, .. }) => {
+                *new = next.clone()
+            }
+            (FieldChange::Points { new, .. }, FieldChange::Points { new: next, .. }) => {
+                *new = *next
+            }
+            (
+                FieldChange::Projects { added, removed },
+                FieldChange::Projects {
+                    added: next_added,
+                    removed: next_removed,
+                },
+            ) => {
+                added.retain(|p| !next_removed.contains(p));
+                removed.retain(|p| !next_added.contains(p));
+                for p in next_added {
+                    if !added.contains(p) {
+                        added.push(p.clone());
+                    }
+                }
+                for p in next_removed {
+                    if !removed.contains(p) {
+                        removed.push(p.clone());
+                    }
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn is_noop(&self) -> bool {
+        match self {
+            FieldChange::Status { old, new } => old == new,
+            FieldChange::Owner { old, new } => old == new,
+            FieldChange::Projects { added, removed } => added.is_empty() && removed.is_empty(),
+            FieldChange::Points { old, new } => old == new,
+            FieldChange::Comment { .. } => false,
+        }
+    }
+
+    fn transactions(&self) -> Vec<Transaction> {
+        match self.clone() {
+            FieldChange::Status { new, .. } => vec![Transaction::Status(new)],
+            FieldChange::Owner { new, .. } => vec![Transaction::Owner(new)],
+            FieldChange::Projects { added, removed } => {
+                let mut transactions = Vec::new();
+                if !added.is_empty() {
+                    transactions.push(Transaction::AddProjects(added));
+                }
+                if !removed.is_empty() {
+                    transactions.push(Transaction::RemoveProjects(removed));
+                }
+                transactions
+            }
+            FieldChange::Points { new, .. } => vec![Transaction::Points(new)],
+            FieldChange::Comment { text } => vec![Transaction::Comment(text)],
+        }
+    }
+
+    fn apply_local(&self, task: &Task) {
+        match self.clone() {
+            FieldChange::Status { new, .. } => task.set_status(new),
+            FieldChange::Owner { new, .. } => task.set_owner(new),
+            FieldChange::Projects { .. } => task.forget_projects(),
+            FieldChange::Points { new, .. } => task.set_points(new),
+            FieldChange::Comment { .. } => (),
+        }
+    }
+}
+
+/// Set of changes to plan for a group of tasks
+#[derive(Clone, Debug, Default)]
+pub struct BulkEdit {
+    changes: Vec<BulkChange>,
+}
+
+impl BulkEdit {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn change(mut self, change: BulkChange) -> Self {
+        self.changes.push(change);
+        self
+    }
+
+    pub fn status<S: Into<String>>(self, status: S) -> Self {
+        self.change(BulkChange::Status(status.into()))
+    }
+
+    pub fn owner(self, owner: Option<Phid>) -> Self {
+        self.change(BulkChange::Owner(owner))
+    }
+
+    pub fn reassign(self, from: Option<Phid>, to: Option<Phid>) -> Self {
+        self.change(BulkChange::Reassign { from, to })
+    }
+
+    pub fn add_project(self, project: Phid) -> Self {
+        self.change(BulkChange::AddProject(project))
+    }
+
+    pub fn remove_project(self, project: Phid) -> Self {
+        self.change(BulkChange::RemoveProject(project))
+    }
+
+    pub fn move_project(self, from: Phid, to: Phid) -> Self {
+        self.change(BulkChange::MoveProject { from, to })
+    }
+
+    pub fn points(self, points: Option<Decimal>) -> Self {
+        self.change(BulkChange::Points(points))
+    }
+
+    pub fn comment<S: Into<String>>(self, comment: S) -> Self {
+        self.change(BulkChange::Comment(comment.into()))
+    }
+
+    fn touches_projects(&self) -> bool {
+        self.changes.iter().any(|c| {
+            matches!(
+                c,
+                BulkChange::AddProject(_)
+                    | BulkChange::RemoveProject(_)
+                    | BulkChange::MoveProject { .. }
+            )
+        })
+    }
+
+    fn task_changes(&self, task: &Task) -> Vec<FieldChange> {
+        let old_projects: Vec<Phid> = task
+            .resolved_projects()
+            .unwrap_or_default()
+            .iter()
+            .map(|p| p.phid().clone())
+            .collect();
+        let mut status = task.status();
+        let mut owner = task.owner();
+        let mut points = task.points();
+        let mut projects = old_projects.clone();
+        let mut comments = Vec::new();
+
+        for change in &self.changes {
+            match change {
+                BulkChange::Status(s) => status = s.clone(),
+                BulkChange::Owner(o) => owner = o.clone(),
+                BulkChange::Reassign { from, to } => {
+                    if owner == *from {
+                        owner = to.clone()
+                    }
+                }
+                BulkChange::AddProject(p) => {
+                    if !projects.contains(p) {
+                        projects.push(p.clone())
+                    }
+                }
+                BulkChange::RemoveProject(p) => projects.retain(|e| e != p),
+                BulkChange::MoveProject { from, to } => {
+                    if projects.contains(from) {
+                        projects.retain(|e| e != from);
+                        if !projects.contains(to) {
+                            projects.push(to.clone())
+                        }
+                    }
+                }
+                BulkChange::Points(p) => points = *p,
+                BulkChange::Comment(c) => comments.push(c.clone()),
+            }
+        }
+
+        let mut changes = Vec::new();
+        if status != task.status() {
+            changes.push(FieldChange::Status {
+                old: task.status(),
+                new: status,
+            });
+        }
+        if owner != task.owner() {
+            changes.push(FieldChange::Owner {
+                old: task.owner(),
+                new: owner,
+            });
+        }
+        let added: Vec<_> = projects
+            .iter()
+            .filter(|p| !old_projects.contains(p))
+            .cloned()
+            .collect();
+        let removed: Vec<_> = old_projects
+            .iter()
+            .filter(|p| !projects.contains(p))
+            .cloned()
+            .collect();
+        if !added.is_empty() || !removed.is_empty() {
+            changes.push(FieldChange::Projects { added, removed });
+        }
+        if points != task.points() {
+            changes.push(FieldChange::Points {
+                old: task.points(),
+                new: points,
+            });
+        }
+
+        // Comments only go to otherwise changed tasks, unless commenting is
+        // all this edit does
+        let only_comments = self
+            .changes
+            .iter()
+            .all(|c| matches!(c, BulkChange::Comment(_)));
+        if !changes.is_empty() || only_comments {
+            changes.extend(
+                comments
+                    .into_iter()
+                    .map(|text| FieldChange::Comment { text }),
+            );
+        }
+        changes
+    }
+
+    /// Work out the changes for each task, without changing anything. Tasks
+    /// that wouldn't change are left out of the plan.
+    pub async fn plan(&self, client: &Client, tasks: &[Task]) -> Result<BulkPlan, RequestError> {
+        // Resolve the current projects of all tasks in one go. The query
+        // returns the cached tasks, which needn't be the ones passed in.
+        let resolved: HashMap<u32, Task> = if self.touches_projects() {
+            let ids: Vec<u32> = tasks.iter().map(Task::id).collect();
+            client
+                .tasks(&ids)
+                .projects()
+                .query()
+                .map_ok(|t| (t.id(), t))
+                .try_collect()
+                .await?
+        } else {
+            HashMap::new()
+        };
+
+        let edits = tasks
+            .iter()
+            .map(|t| resolved.get(&t.id()).unwrap_or(t))
+            .map(|t| TaskEdit {
+                task: t.clone(),
+                changes: self.task_changes(t),
+            })
+            .filter(|e| !e.changes.is_empty())
+            .collect();
+        BulkPlan::new(client, edits).await
+    }
+}
+
+/// Planned changes for a single task
+#[derive(Clone, Debug)]
+pub struct TaskEdit {
+    pub task: Task,
+    pub changes: Vec<FieldChange>,
+}
+
+/// Reviewable set of task edits; its `Display` implementation shows the old
+/// and new value of every changed field
+#[derive(Clone, Debug)]
+pub struct BulkPlan {
+    pub edits: Vec<TaskEdit>,
+    // Project titles for display
+    projects: HashMap<Phid, String>,
+}
+
+impl BulkPlan {
+    async fn new(client: &Client, edits: Vec<TaskEdit>) -> Result<Self, RequestError> {
+        let mut phids: Vec<&Phid> = edits
+            .iter()
+            .flat_map(|e| &e.changes)
+            .filter_map(|c| match c {
+                FieldChange::Projects { added, removed } => Some(added.iter().chain(removed)),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        phids.sort_by(|a, b| a.0.cmp(&b.0));
+        phids.dedup();
+
+        let projects = if phids.is_empty() {
+            HashMap::new()
+        } else {
+            client
+                .projects_by_phid(phids)
+                .query()
+                .map_ok(|p| (p.phid().clone(), p.title()))
+                .try_collect()
+                .await?
+        };
+        Ok(BulkPlan { edits, projects })
+    }
+
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Apply the plan, see [`Apply`] for the options
+    pub fn apply<'a>(&'a self, client: &'a Client) -> Apply<'a> {
+        Apply {
+            plan: self,
+            client,
+            concurrency: 4,
+            progress: None,
+            #[cfg(feature = "serde")]
+            log: None,
+        }
+    }
+
+    fn project_name(&self, phid: &Phid) -> String {
+        self.projects
+            .get(phid)
+            .cloned()
+            .unwrap_or_else(|| phid.0.clone())
+    }
+}
+
+fn or_none<T: ToString>(v: &Option<T>) -> String {
+    v.as_ref()
+        .map_or_else(|| "(none)".to_string(), ToString::to_string)
+}
+
+impl fmt::Display for BulkPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for edit in &self.edits {
+            writeln!(f, "T{} {}", edit.task.id(), edit.task.title())?;
+            for change in &edit.changes {
+                match change {
+                    FieldChange::Status { old, new } => writeln!(f, "  status: {} → {}", old, new)?,
+                    FieldChange::Owner { old, new } => writeln!(
+                        f,
+                        "  owner: {} → {}",
+                        or_none(&old.as_ref().map(|p| &p.0)),
+                        or_none(&new.as_ref().map(|p| &p.0))
+                    )?,
+                    FieldChange::Projects { added, removed } => {
+                        let changes: Vec<_> = added
+                            .iter()
+                            .map(|p| format!("+{}", self.project_name(p)))
+                            .chain(removed.iter().map(|p| format!("-{}", self.project_name(p))))
+                            .collect();
+                        writeln!(f, "  projects: {}", changes.join(", "))?
+                    }
+                    FieldChange::Points { old, new } => {
+                        writeln!(f, "  points: {} → {}", or_none(old), or_none(new))?
+                    }
+                    FieldChange::Comment { text } => writeln!(f, "  comment: {}", text)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Progress of applying a plan, reported after each task
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+    pub task: u32,
+    pub success: bool,
+}
+
+/// Changes applied to a single task
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UndoEntry {
+    pub task: u32,
+    pub changes: Vec<FieldChange>,
+}
+
+/// Record of the applied changes, in order of application
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UndoLog {
+    pub entries: Vec<UndoEntry>,
+}
+
+impl UndoLog {
+    /// Read a log as written by [`Apply::undo_log`], one JSON entry per line
+    #[cfg(feature = "serde")]
+    pub fn read<R: BufRead>(reader: R) -> Result<Self, UndoLogError> {
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                entries.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(UndoLog { entries })
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), UndoLogError> {
+        for e in &self.entries {
+            serde_json::to_writer(&mut writer, e)?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Plan reverting the logged changes. All changes to a task are undone
+    /// by a single edit, so edits applied concurrently can't race.
+    pub async fn revert(&self, client: &Client) -> Result<BulkPlan, RequestError> {
+        let ids: Vec<u32> = self.entries.iter().map(|e| e.task).collect();
+        let tasks: HashMap<u32, Task> = client
+            .tasks(&ids)
+            .query()
+            .map_ok(|t| (t.id(), t))
+            .try_collect()
+            .await?;
+
+        // Undo latest first, so for each field the earliest old value wins
+        let mut edits: Vec<TaskEdit> = Vec::new();
+        for e in self.entries.iter().rev() {
+            let task = match tasks.get(&e.task) {
+                Some(task) => task,
+                None => continue,
+            };
+            let edit = match edits.iter().position(|edit| edit.task.id() == e.task) {
+                Some(i) => &mut edits[i],
+                None => {
+                    edits.push(TaskEdit {
+                        task: task.clone(),
+                        changes: Vec::new(),
+                    });
+                    edits.last_mut().unwrap()
+                }
+            };
+            for change in e.changes.iter().filter_map(FieldChange::inverse) {
+                if !edit.changes.iter_mut().any(|c| c.merge(&change)) {
+                    edit.changes.push(change);
+                }
+            }
+        }
+        for edit in &mut edits {
+            edit.changes.retain(|c| !c.is_noop());
+        }
+        edits.retain(|e| !e.changes.is_empty());
+        BulkPlan::new(client, edits).await
+    }
+}
+
+/// Result of applying a plan
+#[derive(Debug)]
+pub struct BulkReport {
+    pub undo: UndoLog,
+    pub failed: Vec<(Task, RequestError)>,
+}
+
+type ProgressFn<'a> = Box<dyn FnMut(&Progress) + 'a>;
+
+/// Options for applying a [`BulkPlan`]
+pub struct Apply<'a> {
+    plan: &'a BulkPlan,
+    client: &'a Client,
+    concurrency: usize,
+    progress: Option<ProgressFn<'a>>,
+    #[cfg(feature = "serde")]
+    log: Option<Box<dyn Write + 'a>>,
+}
+
+impl<'a> Apply<'a> {
+    /// Maximum number of edits in flight, 4 by default
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn progress<F>(mut self, progress: F) -> Self
+    where
+        F: FnMut(&Progress) + 'a,
+    {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Write every applied edit to `log` as soon as it's done, so the log is
+    /// complete even if applying gets interrupted
+    #[cfg(feature = "serde")]
+    pub fn undo_log<W>(mut self, log: W) -> Self
+    where
+        W: Write + 'a,
+    {
+        self.log = Some(Box::new(log));
+        self
+    }
+
+    /// Apply all edits. Failing edits don't stop the others; they're part of
+    /// the report instead.
+    pub async fn run(mut self) -> Result<BulkReport, UndoLogError> {
+        let client = self.client;
+        let total = self.plan.edits.len();
+        let mut results = stream::iter(&self.plan.edits)
+            .map(|edit| async move {
+                let e = Edit {
+                    object: Some(edit.task.phid().0.clone()),
+                    transactions: edit
+                        .changes
+                        .iter()
+                        .flat_map(FieldChange::transactions)
+                        .collect(),
+                };
+                let r = client.client().request(&e).await;
+                (edit, r)
+            })
+            .buffer_unordered(self.concurrency);
+
+        let mut report = BulkReport {
+            undo: UndoLog::default(),
+            failed: Vec::new(),
+        };
+        let mut done = 0;
+        while let Some((edit, r)) = results.next().await {
+            done += 1;
+            let success = r.is_ok();
+            match r {
+                Ok(_) => {
+                    for c in &edit.changes {
+                        c.apply_local(&edit.task);
+                    }
+                    let entry = UndoEntry {
+                        task: edit.task.id(),
+                        changes: edit.changes.clone(),
+                    };
+                    #[cfg(feature = "serde")]
+                    if let Some(log) = &mut self.log {
+                        serde_json::to_writer(&mut *log, &entry)?;
+                        writeln!(log)?;
+                        log.flush()?;
+                    }
+                    report.undo.entries.push(entry);
+                }
+                Err(e) => report.failed.push((edit.task.clone(), e)),
+            }
+            if let Some(progress) = &mut self.progress {
+                progress(&Progress {
+                    done,
+                    total,
+                    task: edit.task.id(),
+                    success,
+                });
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use phabricator_mock::PhabMockServer;
+
+    struct Setup {
+        m: PhabMockServer,
+        client: Client,
+        tasks: Vec<Task>,
+        x: Phid,
+        y: Phid,
+        sprint12: Phid,
+        sprint13: Phid,
+    }
+
+    async fn setup() -> Setup {
+        let m = PhabMockServer::start().await;
+        let x = m.new_user("x", "User X");
+        let y = m.new_user("y", "User Y");
+        let mut projects = Vec::new();
+        for (id, name) in [(12, "Sprint 12"), (13, "Sprint 13")] {
+            let p = phabricator_mock::project()
+                .id(id)
+                .name(name)
+                .build()
+                .unwrap();
+            m.add_project(p.clone());
+            projects.push(p);
+        }
+
+        for id in 1..=4 {
+            let t = phabricator_mock::task()
+                .id(id)
+                .full_name(format!("Task {}", id))
+                .description("")
+                .author(x.clone())
+                .owner(if id % 2 == 0 { y.clone() } else { x.clone() })
+                .status(m.default_status())
+                .priority(m.default_priority())
+                .projects(if id < 4 {
+                    vec![projects[0].clone()]
+                } else {
+                    vec![]
+                })
+                .build()
+                .unwrap();
+            m.add_task(t);
+        }
+
+        let client = Client::new(m.uri(), m.token().to_string());
+        let mut tasks: Vec<Task> = client
+            .tasks(&[1, 2, 3, 4])
+            .query()
+            .try_collect()
+            .await
+            .unwrap();
+        tasks.sort_by_key(Task::id);
+        let phid = |s: String| Phid(s);
+        Setup {
+            client,
+            tasks,
+            x: phid(x.phid.to_string()),
+            y: phid(y.phid.to_string()),
+            sprint12: phid(projects[0].phid.to_string()),
+            sprint13: phid(projects[1].phid.to_string()),
+            m,
+        }
+    }
+
+    #[tokio::test]
+    async fn plan() {
+        let s = setup().await;
+        let edit = BulkEdit::new()
+            .move_project(s.sprint12.clone(), s.sprint13.clone())
+            .reassign(Some(s.x.clone()), Some(s.y.clone()))
+            .comment("Moved to the next sprint");
+        let plan = edit.plan(&s.client, &s.tasks).await.unwrap();
+
+        // Task 4 is neither in sprint 12 nor owned by X
+        assert_eq!(
+            vec![1, 2, 3],
+            plan.edits.iter().map(|e| e.task.id()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                FieldChange::Projects {
+                    added: vec![s.sprint13.clone()],
+                    removed: vec![s.sprint12.clone()]
+                },
+                FieldChange::Comment {
+                    text: "Moved to the next sprint".to_string()
+                }
+            ],
+            plan.edits[1].changes
+        );
+
+        let shown = plan.to_string();
+        assert!(shown.starts_with(&format!(
+            "T1 Task 1\n  owner: {} → {}\n  projects: +Sprint 13, -Sprint 12\n",
+            s.x.0, s.y.0
+        )));
+        // Planning doesn't change anything
+        assert_eq!(
+            s.x.0,
+            s.m.get_task(1)
+                .unwrap()
+                .owner
+                .as_ref()
+                .unwrap()
+                .phid
+                .to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn plan_uncached() {
+        let s = setup().await;
+        let tasks: Vec<Task> = s
+            .client
+            .tasks(&[1, 2, 3, 4])
+            .uncached()
+            .query()
+            .try_collect()
+            .await
+            .unwrap();
+        let plan = BulkEdit::new()
+            .move_project(s.sprint12.clone(), s.sprint13.clone())
+            .plan(&s.client, &tasks)
+            .await
+            .unwrap();
+        let mut ids: Vec<_> = plan.edits.iter().map(|e| e.task.id()).collect();
+        ids.sort_unstable();
+        assert_eq!(vec![1, 2, 3], ids);
+    }
+
+    #[tokio::test]
+    async fn apply_and_revert() {
+        let s = setup().await;
+        let plan = BulkEdit::new()
+            .move_project(s.sprint12.clone(), s.sprint13.clone())
+            .status("wip")
+            .points(Some(Decimal::new(3, 0)))
+            .plan(&s.client, &s.tasks)
+            .await
+            .unwrap();
+        assert_eq!(4, plan.len());
+
+        #[cfg(feature = "serde")]
+        let mut log = Vec::new();
+        let mut progress = Vec::new();
+        let apply = plan
+            .apply(&s.client)
+            .concurrency(2)
+            .progress(|p| progress.push((p.done, p.total, p.success)));
+        #[cfg(feature = "serde")]
+        let apply = apply.undo_log(&mut log);
+        let report = apply.run().await.unwrap();
+        assert!(report.failed.is_empty());
+        assert_eq!(
+            vec![(1, 4, true), (2, 4, true), (3, 4, true), (4, 4, true)],
+            progress
+        );
+
+        let t = s.m.get_task(1).unwrap();
+        assert_eq!("wip", t.status.value);
+        assert_eq!(Some(Decimal::new(3, 0)), t.points);
+        assert_eq!(
+            vec![s.sprint13.0.clone()],
+            vec![t.projects[0].phid.to_string()]
+        );
+        assert_eq!("wip", s.tasks[0].status());
+        assert_eq!("Sprint 13", s.tasks[0].projects().await.unwrap()[0].title());
+
+        let undo = report.undo;
+        #[cfg(feature = "serde")]
+        assert_eq!(undo, UndoLog::read(log.as_slice()).unwrap());
+
+        let revert = undo.revert(&s.client).await.unwrap();
+        assert_eq!(4, revert.len());
+        let report = revert.apply(&s.client).run().await.unwrap();
+        assert!(report.failed.is_empty());
+
+        for id in 1..=4 {
+            let t = s.m.get_task(id).unwrap();
+            assert_eq!("open", t.status.value);
+            assert_eq!(None, t.points);
+        }
+        let t = s.m.get_task(1).unwrap();
+        assert_eq!(s.sprint12.0, t.projects[0].phid.to_string());
+        assert!(s.m.get_task(4).unwrap().projects.is_empty());
+    }
+
+    #[tokio::test]
+    async fn revert_repeated_edits() {
+        let s = setup().await;
+        let mut undo = UndoLog::default();
+        for (status, points) in [("wip", 1), ("closed", 2)] {
+            let plan = BulkEdit::new()
+                .status(status)
+                .points(Some(Decimal::new(points, 0)))
+                .move_project(s.sprint12.clone(), s.sprint13.clone())
+                .plan(&s.client, &s.tasks)
+                .await
+                .unwrap();
+            let report = plan.apply(&s.client).run().await.unwrap();
+            assert!(report.failed.is_empty());
+            undo.entries.extend(report.undo.entries);
+        }
+        assert_eq!("closed", s.m.get_task(1).unwrap().status.value);
+
+        assert_eq!(8, undo.entries.len());
+        let revert = undo.revert(&s.client).await.unwrap();
+        // A single edit per task
+        assert_eq!(4, revert.len());
+        assert_eq!(
+            vec![
+                FieldChange::Status {
+                    old: "closed".to_string(),
+                    new: "open".to_string()
+                },
+                FieldChange::Points {
+                    old: Some(Decimal::new(2, 0)),
+                    new: None
+                },
+                FieldChange::Projects {
+                    added: vec![s.sprint12.clone()],
+                    removed: vec![s.sprint13.clone()]
+                },
+            ],
+            revert
+                .edits
+                .iter()
+                .find(|e| e.task.id() == 1)
+                .unwrap()
+                .changes
+        );
+
+        let report = revert.apply(&s.client).concurrency(8).run().await.unwrap();
+        assert!(report.failed.is_empty());
+        for id in 1..=4 {
+            let t = s.m.get_task(id).unwrap();
+            assert_eq!("open", t.status.value);
+            assert_eq!(None, t.points);
+        }
+        assert_eq!(
+            s.sprint12.0,
+            s.m.get_task(1).unwrap().projects[0].phid.to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn failures() {
+        let s = setup().await;
+        let plan = BulkEdit::new()
+            .status("nonexistent")
+            .plan(&s.client, &s.tasks)
+            .await
+            .unwrap();
+        let report = plan.apply(&s.client).run().await.unwrap();
+        assert_eq!(4, report.failed.len());
+        assert!(report.undo.entries.is_empty());
+        assert_eq!("open", s.tasks[0].status());
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{Project, Task};
+use lru::LruCache;
+use phabricator_api::types::Phid;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+#[derive(Debug)]
+pub(crate) struct Cache {
+    tasks: LruCache<u32, Task>,
+    task_phids: HashMap<Phid, u32>,
+    pub(crate) projects: HashMap<Phid, Project>,
+    stats: CacheStats,
+}
+
+impl Cache {
+    pub(crate) fn new() -> Self {
+        Cache {
+            tasks: LruCache::unbounded(),
+            task_phids: HashMap::new(),
+            projects: HashMap::new(),
+            stats: Default::default(),
+        }
+    }
+
+    fn record<T>(&mut self, v: Option<T>) -> Option<T> {
+        if v.is_some() {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+        v
+    }
+
+    pub(crate) fn task(&mut self, id: u32) -> Option<Task> {
+        let task = self.tasks.get(&id).cloned();
+        self.record(task)
+    }
+
+    pub(crate) fn task_by_phid(&mut self, phid: &Phid) -> Option<Task> {
+        let task = match self.task_phids.get(phid) {
+            Some(id) => self.tasks.get(id).cloned(),
+            None => None,
+        };
+        self.record(task)
+    }
+
+    pub(crate) fn project(&mut self, phid: &Phid) -> Option<Project> {
+        let project = self.projects.get(phid).cloned();
+        self.record(project)
+    }
+
+    pub(crate) fn task_mut(&mut self, id: u32) -> Option<&mut Task> {
+        self.tasks.get_mut(&id)
+    }
+
+    pub(crate) fn insert_task(&mut self, task: Task) {
+        let id = task.id();
+        let phid = task.phid().clone();
+        if let Some((evicted_id, evicted)) = self.tasks.push(id, task) {
+            // push also hands back the previous value when replacing
+            if evicted_id != id {
+                self.task_phids.remove(evicted.phid());
+                self.stats.evictions += 1;
+            }
+        }
+        if self.tasks.contains(&id) {
+            self.task_phids.insert(phid, id);
+        }
+    }
+
+    #[cfg(feature = "cache-store")]
+    pub(crate) fn tasks(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.iter().map(|(_, t)| t)
+    }
+
+    pub(crate) fn set_capacity(&mut self, capacity: Option<usize>) {
+        let capacity = capacity.unwrap_or(usize::MAX);
+        while self.tasks.len() > capacity {
+            if let Some((_, evicted)) = self.tasks.pop_lru() {
+                self.task_phids.remove(evicted.phid());
+                self.stats.evictions += 1;
+            }
+        }
+        self.tasks.resize(capacity);
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        self.stats
+    }
+}
@@ -0,0 +1,73 @@
+use crate::remarkup::Document;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChecklistItem {
+    pub checked: bool,
+    pub text: String,
+}
+
+/// `[ ]`/`[x]` items of a Remarkup document
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Checklist {
+    pub items: Vec<ChecklistItem>,
+}
+
+impl Checklist {
+    pub fn from_document(doc: &Document) -> Self {
+        let items = doc
+            .checklist()
+            .iter()
+            .map(|item| ChecklistItem {
+                checked: item.checked == Some(true),
+                text: item.text(),
+            })
+            .collect();
+        Checklist { items }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Number of checked items
+    pub fn done(&self) -> usize {
+        self.items.iter().filter(|i| i.checked).count()
+    }
+
+    /// Fraction of checked items, `None` without any items
+    pub fn ratio(&self) -> Option<f64> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.done() as f64 / self.len() as f64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::remarkup::parse;
+
+    #[test]
+    fn ratio() {
+        let doc = parse("Steps:\n\n- [x] one\n- [ ] two\n  - [X] nested\n- not an item");
+        let checklist = Checklist::from_document(&doc);
+        assert_eq!(
+            vec![(true, "one"), (false, "two"), (true, "nested")],
+            checklist
+                .items
+                .iter()
+                .map(|i| (i.checked, i.text.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(2, checklist.done());
+        assert_eq!(Some(2.0 / 3.0), checklist.ratio());
+
+        assert_eq!(None, Checklist::from_document(&parse("Nothing")).ratio());
+    }
+}
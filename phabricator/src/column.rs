@@ -0,0 +1,12 @@
+use phabricator_api::types::Phid;
+
+/// Workboard column a task is in
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Column {
+    pub id: u32,
+    pub phid: Phid,
+    pub name: String,
+    /// Project the workboard belongs to
+    pub board: Phid,
+}
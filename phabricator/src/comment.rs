@@ -0,0 +1,11 @@
+use chrono::{DateTime, Utc};
+use phabricator_api::types::Phid;
+
+/// Comment on a task, in its latest version
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Comment {
+    pub id: u32,
+    pub author: Phid,
+    pub created: DateTime<Utc>,
+    pub content: String,
+}
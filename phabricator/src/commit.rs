@@ -0,0 +1,304 @@
+use crate::{Client, Reference, Task};
+use futures::prelude::*;
+use phabricator_api::maniphest::edit::{Edit, Transaction};
+use phabricator_api::phid::Lookup;
+use phabricator_api::RequestError;
+use std::collections::HashMap;
+
+// Verbs Phabricator recognizes in commit messages, with the status closing
+// verbs move the task to
+const CLOSING: &[(&str, &str)] = &[
+    ("close", "resolved"),
+    ("closes", "resolved"),
+    ("closed", "resolved"),
+    ("closing", "resolved"),
+    ("fix", "resolved"),
+    ("fixes", "resolved"),
+    ("fixed", "resolved"),
+    ("fixing", "resolved"),
+    ("resolve", "resolved"),
+    ("resolves", "resolved"),
+    ("resolved", "resolved"),
+    ("resolving", "resolved"),
+    ("wontfix", "wontfix"),
+    ("wontfixes", "wontfix"),
+    ("wontfixed", "wontfix"),
+    ("invalidate", "invalid"),
+    ("invalidates", "invalid"),
+    ("invalidated", "invalid"),
+    ("spite", "spite"),
+    ("spites", "spite"),
+    ("spited", "spite"),
+];
+const REFERENCING: &[&str] = &["ref", "refs", "references", "cf."];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TaskAction {
+    Reference,
+    /// Close the task with the given status value
+    Close(String),
+}
+
+/// Task and revision references in a commit message
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommitReferences {
+    /// Task ids in order of appearance
+    pub tasks: Vec<(u32, TaskAction)>,
+    /// Revision id from the `Differential Revision:` trailer
+    pub revision: Option<u32>,
+}
+
+fn action(verb: &str) -> Option<TaskAction> {
+    let verb = verb.to_lowercase();
+    if let Some((_, status)) = CLOSING.iter().find(|(v, _)| *v == verb) {
+        return Some(TaskAction::Close(status.to_string()));
+    }
+    REFERENCING
+        .iter()
+        .any(|v| *v == verb || v.strip_suffix('.') == Some(&verb))
+        .then_some(TaskAction::Reference)
+}
+
+fn task_id(word: &str) -> Option<u32> {
+    word.trim_end_matches(|c: char| ".:;!?)".contains(c))
+        .strip_prefix('T')?
+        .parse()
+        .ok()
+}
+
+fn revision_id(value: &str) -> Option<u32> {
+    // Either a bare monogram or the revision url
+    let monogram = value.trim().trim_end_matches('/').rsplit('/').next()?;
+    monogram.strip_prefix('D')?.parse().ok()
+}
+
+/// Find task references like `Fixes T123, T124` or `Ref T456` and the
+/// `Differential Revision:` trailer. Lines starting with `#` are skipped like
+/// git does.
+pub fn parse(message: &str) -> CommitReferences {
+    let mut refs = CommitReferences::default();
+
+    for line in message.lines().filter(|l| !l.starts_with('#')) {
+        if let Some(value) = line.strip_prefix("Differential Revision:") {
+            refs.revision = revision_id(value);
+            continue;
+        }
+
+        let words: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || c == ',' || c == '(')
+            .filter(|w| !w.is_empty())
+            .collect();
+        let mut i = 0;
+        while i < words.len() {
+            let action = match action(words[i]) {
+                Some(action) => action,
+                None => {
+                    i += 1;
+                    continue;
+                }
+            };
+            i += 1;
+            if i < words.len() && matches!(words[i].to_lowercase().as_str(), "task" | "tasks") {
+                i += 1;
+            }
+            // A list like `T1, T2 and T3`
+            while i < words.len() {
+                if let Some(id) = task_id(words[i]) {
+                    match refs.tasks.iter_mut().find(|(t, _)| *t == id) {
+                        // Closing wins over just referencing
+                        Some((_, existing)) => {
+                            if *existing == TaskAction::Reference {
+                                *existing = action.clone()
+                            }
+                        }
+                        None => refs.tasks.push((id, action.clone())),
+                    }
+                } else if !words[i].eq_ignore_ascii_case("and") {
+                    break;
+                }
+                i += 1;
+            }
+        }
+    }
+    refs
+}
+
+/// Commit references resolved against the server
+#[derive(Clone, Debug)]
+pub struct ResolvedCommit {
+    pub tasks: Vec<(Task, TaskAction)>,
+    pub revision: Option<Reference>,
+}
+
+impl ResolvedCommit {
+    /// Post `comment` on every referenced task and, if `close` is set,
+    /// close the tasks referenced with a closing verb that are still open.
+    /// Returns the closed tasks.
+    pub async fn apply(
+        &self,
+        client: &Client,
+        comment: &str,
+        close: bool,
+    ) -> Result<Vec<Task>, RequestError> {
+        let mut closed = Vec::new();
+        for (task, action) in &self.tasks {
+            let mut transactions = vec![Transaction::Comment(comment.to_string())];
+            let status = match action {
+                TaskAction::Close(status) if close && task.closed().is_none() => Some(status),
+                _ => None,
+            };
+            if let Some(status) = status {
+                transactions.push(Transaction::Status(status.clone()));
+            }
+
+            let e = Edit {
+                object: Some(task.phid().0.clone()),
+                transactions,
+            };
+            client.client().request(&e).await?;
+            if let Some(status) = status {
+                task.set_status(status.clone());
+                closed.push(task.clone());
+            }
+        }
+        Ok(closed)
+    }
+}
+
+impl Client {
+    /// Look up the tasks and revision referenced by a commit message. Unknown
+    /// references are left out.
+    pub async fn resolve_commit(&self, message: &str) -> Result<ResolvedCommit, RequestError> {
+        let refs = parse(message);
+        let ids: Vec<u32> = refs.tasks.iter().map(|(id, _)| *id).collect();
+        let mut tasks: HashMap<u32, Task> = self
+            .tasks(&ids)
+            .query()
+            .map_ok(|t| (t.id(), t))
+            .try_collect()
+            .await?;
+
+        let revision = match refs.revision {
+            Some(id) => {
+                let name = format!("D{}", id);
+                let mut r = self
+                    .client()
+                    .request(&Lookup {
+                        names: vec![name.clone()],
+                    })
+                    .await?;
+                r.0.remove(&name).map(Reference::from)
+            }
+            None => None,
+        };
+
+        Ok(ResolvedCommit {
+            tasks: refs
+                .tasks
+                .into_iter()
+                .filter_map(|(id, action)| Some((tasks.remove(&id)?, action)))
+                .collect(),
+            revision,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "reqwest")]
+    use phabricator_mock::PhabMockServer;
+
+    fn close(id: u32, status: &str) -> (u32, TaskAction) {
+        (id, TaskAction::Close(status.to_string()))
+    }
+
+    #[test]
+    fn parsing() {
+        let message = "Fix the frobnicator\n\n\
+                       This fixes T12, T13 and T14. Ref T15 (cf. T16)\n\
+                       Invalidates task T17; Wontfix T18.\n\
+                       refs T12\n\
+                       # Closes T99\n\
+                       Mentions T19 without a verb, closes nothing\n\n\
+                       Differential Revision: https://phabricator.example.com/D789\n";
+        let refs = parse(message);
+        assert_eq!(
+            vec![
+                close(12, "resolved"),
+                close(13, "resolved"),
+                close(14, "resolved"),
+                (15, TaskAction::Reference),
+                (16, TaskAction::Reference),
+                close(17, "invalid"),
+                close(18, "wontfix"),
+            ],
+            refs.tasks
+        );
+        assert_eq!(Some(789), refs.revision);
+
+        assert_eq!(Some(5), parse("Differential Revision: D5").revision);
+        assert_eq!(CommitReferences::default(), parse("Refactor T-shirts"));
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn apply() {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        let resolved = phabricator_mock::status()
+            .value("resolved")
+            .name("Resolved")
+            .closed(true)
+            .build()
+            .unwrap();
+        m.add_status(resolved);
+        for id in [1, 2] {
+            m.new_simple_task(id, &user);
+        }
+
+        let client = Client::new(m.uri(), m.token().to_string());
+        let commit = client
+            .resolve_commit("Fixes T1, T404\n\nRef T2\n\nDifferential Revision: D7")
+            .await
+            .unwrap();
+        assert_eq!(
+            vec![
+                (1, TaskAction::Close("resolved".to_string())),
+                (2, TaskAction::Reference)
+            ],
+            commit
+                .tasks
+                .iter()
+                .map(|(t, a)| (t.id(), a.clone()))
+                .collect::<Vec<_>>()
+        );
+        // The mock doesn't know about revisions
+        assert!(commit.revision.is_none());
+
+        let closed = commit
+            .apply(&client, "Mentioned in rXabc", false)
+            .await
+            .unwrap();
+        assert!(closed.is_empty());
+        assert_eq!("open", m.get_task(1).unwrap().status.value);
+
+        let closed = commit
+            .apply(&client, "Closed by rXabc", true)
+            .await
+            .unwrap();
+        assert_eq!(vec![1], closed.iter().map(Task::id).collect::<Vec<_>>());
+        let t1 = m.get_task(1).unwrap();
+        assert_eq!("resolved", t1.status.value);
+        assert_eq!(
+            vec!["Mentioned in rXabc", "Closed by rXabc"],
+            t1.comments()
+                .iter()
+                .map(|c| c.content.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("open", m.get_task(2).unwrap().status.value);
+        assert_eq!(2, m.get_task(2).unwrap().comments().len());
+        assert_eq!("resolved", closed[0].status());
+    }
+}
@@ -0,0 +1,417 @@
+use crate::{Project, Task};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+use std::fmt::Write;
+
+pub(crate) fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+// `\|` keeps a pipe inside a Markdown cell. Remarkup has no such escape, so
+// there a cell containing `|` still ends early.
+fn table_cell(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", " ")
+        .replace(['\n', '\r'], " ")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    /// One JSON object per line, keyed by the conduit field names
+    JsonLines,
+    /// Pipe table, understood by both Markdown and Remarkup. Pipes in values
+    /// are escaped for Markdown only; Remarkup can't escape them in tables.
+    Table,
+}
+
+/// A column of an export
+pub trait Field: Copy {
+    type Item;
+
+    fn name(self) -> &'static str;
+    fn value(self, item: &Self::Item) -> Value;
+
+    /// Value as shown in CSV and table cells
+    fn text(self, item: &Self::Item) -> String {
+        value_text(self.value(item))
+    }
+}
+
+fn value_text(value: Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s,
+        Value::Array(a) => a.into_iter().map(value_text).collect::<Vec<_>>().join(", "),
+        v => v.to_string(),
+    }
+}
+
+fn date(d: DateTime<Utc>) -> String {
+    d.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskField {
+    Id,
+    Phid,
+    Title,
+    Description,
+    Status,
+    Owner,
+    Points,
+    Created,
+    Modified,
+    Closed,
+    /// Project titles, only known for tasks queried with their projects
+    Projects,
+    /// Column names, only known for tasks queried with their columns
+    Columns,
+}
+
+impl TaskField {
+    pub const ALL: [TaskField; 12] = [
+        TaskField::Id,
+        TaskField::Phid,
+        TaskField::Title,
+        TaskField::Description,
+        TaskField::Status,
+        TaskField::Owner,
+        TaskField::Points,
+        TaskField::Created,
+        TaskField::Modified,
+        TaskField::Closed,
+        TaskField::Projects,
+        TaskField::Columns,
+    ];
+}
+
+impl Field for TaskField {
+    type Item = Task;
+
+    fn name(self) -> &'static str {
+        match self {
+            TaskField::Id => "id",
+            TaskField::Phid => "phid",
+            TaskField::Title => "name",
+            TaskField::Description => "description",
+            TaskField::Status => "status",
+            TaskField::Owner => "ownerPHID",
+            TaskField::Points => "points",
+            TaskField::Created => "dateCreated",
+            TaskField::Modified => "dateModified",
+            TaskField::Closed => "dateClosed",
+            TaskField::Projects => "projects",
+            TaskField::Columns => "columns",
+        }
+    }
+
+    fn value(self, task: &Task) -> Value {
+        match self {
+            TaskField::Id => json!(task.id()),
+            TaskField::Phid => json!(task.phid().0),
+            TaskField::Title => json!(task.title()),
+            TaskField::Description => json!(task.description()),
+            TaskField::Status => json!(task.status()),
+            TaskField::Owner => json!(task.owner().map(|o| o.0)),
+            // Decimal as string to not lose precision
+            TaskField::Points => json!(task.points().map(|p| p.to_string())),
+            TaskField::Created => json!(task.created().timestamp()),
+            TaskField::Modified => json!(task.modified().timestamp()),
+            TaskField::Closed => json!(task.closed().map(|c| c.timestamp())),
+            TaskField::Projects => json!(task
+                .resolved_projects()
+                .map(|p| p.iter().map(Project::title).collect::<Vec<_>>())),
+            TaskField::Columns => json!(task
+                .columns()
+                .map(|c| c.into_iter().map(|c| c.name).collect::<Vec<_>>())),
+        }
+    }
+
+    fn text(self, task: &Task) -> String {
+        match self {
+            TaskField::Created => date(task.created()),
+            TaskField::Modified => date(task.modified()),
+            TaskField::Closed => task.closed().map(date).unwrap_or_default(),
+            _ => value_text(self.value(task)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProjectField {
+    Id,
+    Phid,
+    Title,
+    Slug,
+    Description,
+}
+
+impl ProjectField {
+    pub const ALL: [ProjectField; 5] = [
+        ProjectField::Id,
+        ProjectField::Phid,
+        ProjectField::Title,
+        ProjectField::Slug,
+        ProjectField::Description,
+    ];
+}
+
+impl Field for ProjectField {
+    type Item = Project;
+
+    fn name(self) -> &'static str {
+        match self {
+            ProjectField::Id => "id",
+            ProjectField::Phid => "phid",
+            ProjectField::Title => "name",
+            ProjectField::Slug => "slug",
+            ProjectField::Description => "description",
+        }
+    }
+
+    fn value(self, project: &Project) -> Value {
+        match self {
+            ProjectField::Id => json!(project.id()),
+            ProjectField::Phid => json!(project.phid().0),
+            ProjectField::Title => json!(project.title()),
+            ProjectField::Slug => json!(project.slug()),
+            ProjectField::Description => json!(project.description()),
+        }
+    }
+}
+
+/// Serializes tasks or projects with a selection of fields. Next to
+/// exporting a whole collection, the header and rows can be written one by
+/// one, e.g. while consuming a query stream.
+#[derive(Clone, Debug)]
+pub struct Exporter<F> {
+    format: Format,
+    fields: Vec<F>,
+}
+
+impl<F: Field> Exporter<F> {
+    pub fn new(format: Format, fields: &[F]) -> Self {
+        Exporter {
+            format,
+            fields: fields.to_vec(),
+        }
+    }
+
+    /// Header lines, empty for JSON Lines
+    pub fn header(&self) -> String {
+        let names = self.fields.iter().map(|f| f.name());
+        match self.format {
+            Format::Csv => {
+                let names: Vec<_> = names.map(csv_field).collect();
+                format!("{}\n", names.join(","))
+            }
+            Format::JsonLines => String::new(),
+            Format::Table => {
+                let names: Vec<_> = names.collect();
+                let rule = vec!["---"; names.len()];
+                format!("| {} |\n| {} |\n", names.join(" | "), rule.join(" | "))
+            }
+        }
+    }
+
+    /// A single newline terminated row
+    pub fn row(&self, item: &F::Item) -> String {
+        match self.format {
+            Format::Csv => {
+                let cells: Vec<_> = self
+                    .fields
+                    .iter()
+                    .map(|f| csv_field(&f.text(item)))
+                    .collect();
+                format!("{}\n", cells.join(","))
+            }
+            Format::JsonLines => {
+                let object: Map<_, _> = self
+                    .fields
+                    .iter()
+                    .map(|f| (f.name().to_string(), f.value(item)))
+                    .collect();
+                format!("{}\n", Value::Object(object))
+            }
+            Format::Table => {
+                let cells: Vec<_> = self
+                    .fields
+                    .iter()
+                    .map(|f| table_cell(&f.text(item)))
+                    .collect();
+                format!("| {} |\n", cells.join(" | "))
+            }
+        }
+    }
+
+    pub fn export<'a, I>(&self, items: I) -> String
+    where
+        I: IntoIterator<Item = &'a F::Item>,
+        F::Item: 'a,
+    {
+        let mut out = self.header();
+        for item in items {
+            write!(out, "{}", self.row(item)).unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use crate::Client;
+    use futures::prelude::*;
+    use phabricator_mock::PhabMockServer;
+    use rust_decimal::Decimal;
+
+    async fn setup() -> (PhabMockServer, Client) {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        let project = phabricator_mock::project()
+            .id(10)
+            .name("Project")
+            .slug(Some("project".to_string()))
+            .description(Some("A | project".to_string()))
+            .build()
+            .unwrap();
+        m.add_project(project.clone());
+
+        let t = phabricator_mock::task()
+            .id(100)
+            .full_name("Fix \"this\", now")
+            .description("Line 1\nLine 2")
+            .points(Decimal::new(25, 1))
+            .author(user.clone())
+            .owner(user.clone())
+            .priority(m.default_priority())
+            .status(m.default_status())
+            .date_created(1614556800)
+            .date_modified(1614643200)
+            .projects(vec![project])
+            .build()
+            .unwrap();
+        m.add_task(t);
+        m.new_simple_task(200, &user);
+
+        let client = Client::new(m.uri(), m.token().to_string());
+        (m, client)
+    }
+
+    async fn tasks(client: &Client) -> Vec<Task> {
+        let mut tasks: Vec<Task> = client
+            .tasks(&[100, 200])
+            .projects()
+            .query()
+            .try_collect()
+            .await
+            .unwrap();
+        tasks.sort_by_key(Task::id);
+        tasks
+    }
+
+    #[tokio::test]
+    async fn csv() {
+        let (_m, client) = setup().await;
+        let tasks = tasks(&client).await;
+        let exporter = Exporter::new(
+            Format::Csv,
+            &[
+                TaskField::Id,
+                TaskField::Title,
+                TaskField::Points,
+                TaskField::Created,
+                TaskField::Closed,
+                TaskField::Projects,
+            ],
+        );
+
+        let csv = exporter.export(&tasks);
+        let mut lines = csv.lines();
+        assert_eq!(
+            Some("id,name,points,dateCreated,dateClosed,projects"),
+            lines.next()
+        );
+        assert_eq!(
+            Some("100,\"Fix \"\"this\"\", now\",2.5,2021-03-01T00:00:00Z,,Project"),
+            lines.next()
+        );
+        assert!(lines.next().unwrap().starts_with("200,"));
+        assert_eq!(None, lines.next());
+    }
+
+    #[tokio::test]
+    async fn json_lines() {
+        let (m, client) = setup().await;
+        let tasks = tasks(&client).await;
+        let exporter = Exporter::new(Format::JsonLines, &TaskField::ALL);
+
+        assert_eq!("", exporter.header());
+        let out = exporter.export(&tasks);
+        let rows: Vec<Value> = out
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(2, rows.len());
+
+        let owner = m.get_task(100).unwrap().owner.clone().unwrap();
+        let row = &rows[0];
+        assert_eq!(json!(100), row["id"]);
+        assert_eq!(json!("Fix \"this\", now"), row["name"]);
+        assert_eq!(json!("Line 1\nLine 2"), row["description"]);
+        assert_eq!(json!(owner.phid.to_string()), row["ownerPHID"]);
+        assert_eq!(json!("2.5"), row["points"]);
+        assert_eq!(json!(1614556800), row["dateCreated"]);
+        assert_eq!(json!(1614643200), row["dateModified"]);
+        assert_eq!(Value::Null, row["dateClosed"]);
+        assert_eq!(json!(["Project"]), row["projects"]);
+        // Columns weren't queried
+        assert_eq!(Value::Null, row["columns"]);
+        assert_eq!(
+            TaskField::ALL.len(),
+            row.as_object().unwrap().len(),
+            "Not all fields exported"
+        );
+    }
+
+    #[tokio::test]
+    async fn table() {
+        let (_m, client) = setup().await;
+        let tasks = tasks(&client).await;
+
+        let exporter = Exporter::new(
+            Format::Table,
+            &[TaskField::Id, TaskField::Title, TaskField::Description],
+        );
+        // Rows written straight from the query stream
+        let mut out = exporter.header();
+        let mut stream = client.tasks(&[100]).query();
+        while let Some(t) = stream.try_next().await.unwrap() {
+            out.push_str(&exporter.row(&t));
+        }
+        assert_eq!(
+            "| id | name | description |\n\
+             | --- | --- | --- |\n\
+             | 100 | Fix \"this\", now | Line 1 Line 2 |\n",
+            out
+        );
+
+        let projects = tasks[0].projects().await.unwrap();
+        let exporter = Exporter::new(Format::Table, &ProjectField::ALL);
+        assert_eq!(
+            format!(
+                "| id | phid | name | slug | description |\n\
+                 | --- | --- | --- | --- | --- |\n\
+                 | 10 | {} | Project | project | A \\| project |\n",
+                projects[0].phid().0
+            ),
+            exporter.export(&projects)
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use crate::tree::{Direction, TaskTree};
+use crate::{Client, Task};
+use phabricator_api::phid::Lookup;
+use phabricator_api::types::Phid;
+use phabricator_api::RequestError;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cluster {
+    Project,
+    Owner,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Style {
+    Open,
+    Progress,
+    Resolved,
+    Closed,
+}
+
+impl Style {
+    const ALL: [Style; 4] = [Style::Open, Style::Progress, Style::Resolved, Style::Closed];
+
+    /// Style of the stock statuses; custom statuses fall back to the in
+    /// progress style
+    fn for_status(status: &str) -> Style {
+        match status {
+            "open" => Style::Open,
+            "resolved" => Style::Resolved,
+            "wontfix" | "invalid" | "duplicate" | "spite" => Style::Closed,
+            _ => Style::Progress,
+        }
+    }
+
+    fn class(self) -> &'static str {
+        match self {
+            Style::Open => "open",
+            Style::Progress => "progress",
+            Style::Resolved => "resolved",
+            Style::Closed => "closed",
+        }
+    }
+
+    fn fill(self) -> &'static str {
+        match self {
+            Style::Open => "#ffffff",
+            Style::Progress => "#fff3c4",
+            Style::Resolved => "#c8e6c9",
+            Style::Closed => "#e0e0e0",
+        }
+    }
+}
+
+type ClusterTasks<'a> = (String, String, Vec<&'a Task>);
+
+/// Subtask graph of a set of tasks, exportable as Graphviz DOT or Mermaid
+/// flowchart
+#[derive(Clone, Debug, Default)]
+pub struct TaskGraph {
+    tasks: Vec<Task>,
+    // Parent to subtask
+    edges: Vec<(Phid, Phid)>,
+    cluster: Option<Cluster>,
+    owners: HashMap<Phid, String>,
+}
+
+impl TaskGraph {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn from_tree(tree: &TaskTree) -> Self {
+        let mut graph = TaskGraph::new();
+        for t in tree.tasks() {
+            graph.add_task(t.clone());
+        }
+        for t in tree.tasks() {
+            for c in tree.children(t) {
+                match tree.direction() {
+                    Direction::Subtasks => graph.add_edge(t, &c),
+                    Direction::Parents => graph.add_edge(&c, t),
+                }
+            }
+        }
+        graph
+    }
+
+    pub fn add_task(&mut self, task: Task) {
+        if !self.tasks.iter().any(|t| t.phid() == task.phid()) {
+            self.tasks.push(task);
+        }
+    }
+
+    /// Add a parent to subtask edge, both tasks are added if needed
+    pub fn add_edge(&mut self, parent: &Task, subtask: &Task) {
+        self.add_task(parent.clone());
+        self.add_task(subtask.clone());
+        let edge = (parent.phid().clone(), subtask.phid().clone());
+        if !self.edges.contains(&edge) {
+            self.edges.push(edge);
+        }
+    }
+
+    /// Group tasks in a cluster per project or owner. Clustering by project
+    /// only uses tasks whose projects were already retrieved and puts tasks
+    /// in the cluster of their first project. Owner clusters are labelled
+    /// with the owner's PHID, unless resolved by [`TaskGraph::resolve_owners`].
+    pub fn cluster(mut self, cluster: Cluster) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    /// Look up the names of the task owners, to label owner clusters with
+    pub async fn resolve_owners(mut self, client: &Client) -> Result<Self, RequestError> {
+        let mut names: Vec<String> = Vec::new();
+        for o in self.tasks.iter().filter_map(Task::owner) {
+            if !self.owners.contains_key(&o) && !names.contains(&o.0) {
+                names.push(o.0);
+            }
+        }
+        if names.is_empty() {
+            return Ok(self);
+        }
+
+        let r = client.client().request(&Lookup { names }).await?;
+        for item in r.0.into_values() {
+            self.owners.insert(item.phid, item.full_name);
+        }
+        Ok(self)
+    }
+
+    fn id(&self, phid: &Phid) -> u32 {
+        self.tasks
+            .iter()
+            .find(|t| t.phid() == phid)
+            .map(Task::id)
+            .unwrap()
+    }
+
+    fn details(task: &Task) -> String {
+        match task.points() {
+            Some(points) => format!("{}, {} points", task.status(), points),
+            None => task.status(),
+        }
+    }
+
+    // Clusters in order of first appearance as (key, label, tasks) plus the
+    // tasks not in any cluster
+    fn clusters(&self) -> (Vec<ClusterTasks<'_>>, Vec<&Task>) {
+        let mut clusters: Vec<ClusterTasks> = Vec::new();
+        let mut rest = Vec::new();
+
+        for t in &self.tasks {
+            let key = match self.cluster {
+                Some(Cluster::Project) => t
+                    .resolved_projects()
+                    .and_then(|p| p.first().map(|p| (p.phid().0.clone(), p.title()))),
+                Some(Cluster::Owner) => t.owner().map(|o| {
+                    let label = self.owners.get(&o).cloned().unwrap_or_else(|| o.0.clone());
+                    (o.0, label)
+                }),
+                None => None,
+            };
+
+            match key {
+                Some((key, label)) => match clusters.iter_mut().find(|(k, _, _)| *k == key) {
+                    Some((_, _, tasks)) => tasks.push(t),
+                    None => clusters.push((key, label, vec![t])),
+                },
+                None => rest.push(t),
+            }
+        }
+
+        (clusters, rest)
+    }
+
+    pub fn to_dot(&self) -> String {
+        fn escape(s: &str) -> String {
+            s.replace('\\', "\\\\").replace('"', "\\\"")
+        }
+
+        fn node(out: &mut String, indent: &str, t: &Task) {
+            let style = Style::for_status(&t.status());
+            writeln!(
+                out,
+                "{}T{} [label=\"T{}: {}\\n{}\", fillcolor=\"{}\"];",
+                indent,
+                t.id(),
+                t.id(),
+                escape(&t.title()),
+                escape(&TaskGraph::details(t)),
+                style.fill()
+            )
+            .unwrap();
+        }
+
+        let mut out = String::new();
+        out.push_str("digraph tasks {\n");
+        out.push_str("  node [shape=box, style=\"rounded,filled\"];\n");
+
+        let (clusters, rest) = self.clusters();
+        for (i, (_, label, tasks)) in clusters.iter().enumerate() {
+            writeln!(out, "  subgraph cluster_{} {{", i).unwrap();
+            writeln!(out, "    label=\"{}\";", escape(label)).unwrap();
+            for t in tasks {
+                node(&mut out, "    ", t);
+            }
+            out.push_str("  }\n");
+        }
+        for t in rest {
+            node(&mut out, "  ", t);
+        }
+
+        for (parent, subtask) in &self.edges {
+            writeln!(out, "  T{} -> T{};", self.id(parent), self.id(subtask)).unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        fn escape(s: &str) -> String {
+            s.replace('"', "#quot;")
+        }
+
+        fn node(out: &mut String, indent: &str, t: &Task) {
+            writeln!(
+                out,
+                "{}T{}[\"T{}: {}<br/>{}\"]",
+                indent,
+                t.id(),
+                t.id(),
+                escape(&t.title()),
+                escape(&TaskGraph::details(t)),
+            )
+            .unwrap();
+        }
+
+        let mut out = String::new();
+        out.push_str("flowchart TD\n");
+
+        let (clusters, rest) = self.clusters();
+        for (i, (_, label, tasks)) in clusters.iter().enumerate() {
+            writeln!(out, "  subgraph cluster{} [\"{}\"]", i, escape(label)).unwrap();
+            for t in tasks {
+                node(&mut out, "    ", t);
+            }
+            out.push_str("  end\n");
+        }
+        for t in rest {
+            node(&mut out, "  ", t);
+        }
+
+        for (parent, subtask) in &self.edges {
+            writeln!(out, "  T{} --> T{}", self.id(parent), self.id(subtask)).unwrap();
+        }
+
+        let mut used = HashSet::new();
+        for t in &self.tasks {
+            let style = Style::for_status(&t.status());
+            used.insert(style);
+            writeln!(out, "  class T{} {}", t.id(), style.class()).unwrap();
+        }
+        for style in Style::ALL.iter().filter(|s| used.contains(*s)) {
+            writeln!(
+                out,
+                "  classDef {} fill:{},stroke:#333",
+                style.class(),
+                style.fill()
+            )
+            .unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use crate::Client;
+    use futures::prelude::*;
+    use phabricator_mock::task;
+    use phabricator_mock::PhabMockServer;
+    use rust_decimal::Decimal;
+
+    async fn setup() -> (PhabMockServer, Client) {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        let project = phabricator_mock::project()
+            .id(10)
+            .name("Project \"X\"")
+            .build()
+            .unwrap();
+        m.add_project(project.clone());
+
+        let t100 = phabricator_mock::task()
+            .id(100)
+            .full_name("Release")
+            .description("")
+            .points(Decimal::new(3, 0))
+            .author(user.clone())
+            .owner(user.clone())
+            .priority(m.default_priority())
+            .status(m.default_status())
+            .projects(vec![project])
+            .build()
+            .unwrap();
+        m.add_task(t100.clone());
+
+        let resolved = m.new_status("resolved", "Resolved", None);
+        let t200 = phabricator_mock::task()
+            .id(200)
+            .full_name("Write \"notes\"")
+            .description("")
+            .author(user.clone())
+            .priority(m.default_priority())
+            .status(resolved)
+            .build()
+            .unwrap();
+        m.add_task(t200.clone());
+        task::link(&t100, &t200);
+
+        let client = Client::new(m.uri(), m.token().to_string());
+        (m, client)
+    }
+
+    async fn graph(client: &Client) -> TaskGraph {
+        let tasks: Vec<Task> = client
+            .tasks(&[100])
+            .projects()
+            .query()
+            .try_collect()
+            .await
+            .unwrap();
+        let tree = client.task_tree(&tasks).query().await.unwrap();
+        TaskGraph::from_tree(&tree)
+    }
+
+    #[tokio::test]
+    async fn dot() {
+        let (_m, client) = setup().await;
+        let graph = graph(&client).await;
+
+        let expected = r##"digraph tasks {
+  node [shape=box, style="rounded,filled"];
+  T100 [label="T100: Release\nopen, 3 points", fillcolor="#ffffff"];
+  T200 [label="T200: Write \"notes\"\nresolved", fillcolor="#c8e6c9"];
+  T100 -> T200;
+}
+"##;
+        assert_eq!(expected, graph.to_dot());
+
+        let clustered = graph.cluster(Cluster::Project).to_dot();
+        assert!(clustered.contains(
+            "  subgraph cluster_0 {\n    label=\"Project \\\"X\\\"\";\n    T100 [label="
+        ));
+        assert!(clustered.contains("  T200 [label="));
+    }
+
+    #[tokio::test]
+    async fn mermaid() {
+        let (_m, client) = setup().await;
+        let graph = graph(&client).await;
+
+        let expected = r##"flowchart TD
+  T100["T100: Release<br/>open, 3 points"]
+  T200["T200: Write #quot;notes#quot;<br/>resolved"]
+  T100 --> T200
+  class T100 open
+  class T200 resolved
+  classDef open fill:#ffffff,stroke:#333
+  classDef resolved fill:#c8e6c9,stroke:#333
+"##;
+        assert_eq!(expected, graph.to_mermaid());
+
+        let t100 = client.cached_task(100).unwrap();
+        let owner = t100.owner().unwrap();
+        let graph = graph.cluster(Cluster::Owner);
+        let clustered = graph.to_mermaid();
+        assert!(clustered.contains(&format!("  subgraph cluster0 [\"{}\"]\n    T100[", owner.0)));
+
+        let clustered = graph.resolve_owners(&client).await.unwrap().to_mermaid();
+        assert!(clustered.contains("  subgraph cluster0 [\"user (Test User)\"]\n    T100["));
+    }
+}
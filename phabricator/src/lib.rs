@@ -1,9 +1,14 @@
+pub use phabricator_api::config::{Config, ConfigError};
 use phabricator_api::types::Phid;
 use phabricator_api::Client as ApiClient;
-use std::collections::HashMap;
 use std::sync::{Arc, Mutex, Weak};
+#[cfg(feature = "reqwest")]
 use url::Url;
 
+mod cache;
+use cache::Cache;
+pub use cache::CacheStats;
+
 mod search;
 
 mod task;
@@ -12,12 +17,55 @@ pub use task::Task;
 mod project;
 pub use project::Project;
 
+mod column;
+pub use column::Column;
+
+mod comment;
+pub use comment::Comment;
+
+mod checklist;
+pub use checklist::{Checklist, ChecklistItem};
+
+mod reference;
+pub use reference::Reference;
+
 pub mod tasksbuilder;
 use tasksbuilder::TasksBuilder;
 
 pub mod projectsbuilder;
 use projectsbuilder::ProjectsBuilder;
 
+pub mod tree;
+use tree::TaskTreeBuilder;
+
+pub mod graph;
+
+pub mod export;
+
+pub mod remarkup;
+
+pub mod report;
+
+pub mod commit;
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
+pub mod bulk;
+
+#[cfg(feature = "serde")]
+mod snapshot;
+#[cfg(feature = "serde")]
+pub use snapshot::{ProjectSnapshot, TaskSnapshot};
+
+#[cfg(feature = "template")]
+pub mod template;
+
+#[cfg(feature = "cache-store")]
+mod store;
+#[cfg(feature = "cache-store")]
+pub use store::{CacheStore, FileStore, StoreError, StoredCache};
+
 #[derive(Clone, Debug)]
 pub struct Client {
     inner: Arc<Inner>,
@@ -28,30 +76,40 @@ pub(crate) struct WeakClient {
     inner: Weak<Inner>,
 }
 
-#[derive(Debug)]
-pub(crate) struct Cache {
-    tasks: HashMap<u32, Task>,
-    projects: HashMap<Phid, Project>,
-}
-
 #[derive(Debug)]
 struct Inner {
     client: ApiClient,
     // Changable data
     cache: Mutex<Cache>,
+    #[cfg(feature = "cache-store")]
+    store: Option<Box<dyn CacheStore>>,
 }
 
 impl Client {
+    #[cfg(feature = "reqwest")]
     pub fn new(base: Url, token: String) -> Self {
-        let client = ApiClient::new(base, token);
-        let tasks = HashMap::new();
-        let projects = HashMap::new();
-        let cache = Mutex::new(Cache { tasks, projects });
-        let inner = Arc::new(Inner { client, cache });
+        Self::with_api_client(ApiClient::new(base, token))
+    }
+
+    /// Wrap a low-level client, e.g. one set up with
+    /// [phabricator_api::ClientBuilder]
+    pub fn with_api_client(client: ApiClient) -> Self {
+        let cache = Mutex::new(Cache::new());
+        let inner = Arc::new(Inner {
+            client,
+            cache,
+            #[cfg(feature = "cache-store")]
+            store: None,
+        });
 
         Self { inner }
     }
 
+    #[cfg(feature = "reqwest")]
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.url.clone(), config.token.clone())
+    }
+
     pub(crate) fn downgrade(&self) -> WeakClient {
         WeakClient {
             inner: Arc::downgrade(&self.inner),
@@ -73,16 +131,34 @@ impl Client {
         TasksBuilder::new_by_phids(self, phids)
     }
 
+    /// Walk the subtask (or parent) hierarchy starting from `roots`
+    pub fn task_tree<'a, T>(&self, roots: T) -> TaskTreeBuilder<'_>
+    where
+        T: IntoIterator<Item = &'a Task>,
+    {
+        TaskTreeBuilder::new(self, roots.into_iter().cloned().collect())
+    }
+
     pub fn cached_task(&self, id: u32) -> Option<Task> {
-        self.access_cache(|cache| cache.tasks.get(&id).cloned())
+        self.update_cache(|cache| cache.task(id))
     }
 
     pub fn cached_task_by_phid(&self, phid: &Phid) -> Option<Task> {
-        self.access_cache(|cache| cache.tasks.values().find(|t| t.phid() == phid).cloned())
+        self.update_cache(|cache| cache.task_by_phid(phid))
     }
 
     pub fn cached_project(&self, phid: &Phid) -> Option<Project> {
-        self.access_cache(|cache| cache.projects.get(phid).cloned())
+        self.update_cache(|cache| cache.project(phid))
+    }
+
+    /// Limit the number of cached tasks, evicting the least recently used
+    /// ones first. `None` removes the limit.
+    pub fn set_cache_capacity(&self, capacity: Option<usize>) {
+        self.update_cache(|cache| cache.set_capacity(capacity))
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.access_cache(|cache| cache.stats())
     }
 
     pub(crate) fn client(&self) -> &ApiClient {
@@ -119,7 +195,7 @@ impl WeakClient {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "reqwest"))]
 mod test {
     use super::*;
     use futures::prelude::*;
@@ -211,6 +287,78 @@ mod test {
         assert_eq!(1, m.n_requests().await);
     }
 
+    #[tokio::test]
+    async fn cache_phid_lookup() {
+        let m = setup().await;
+        let client = Client::new(m.uri(), m.token().to_string());
+
+        let tasks: Vec<Task> = client.tasks(&[100]).query().try_collect().await.unwrap();
+        let phid = tasks[0].phid().clone();
+
+        let task = client.cached_task_by_phid(&phid).unwrap();
+        assert_eq!(100, task.id());
+    }
+
+    #[tokio::test]
+    async fn cache_capacity() {
+        let m = setup().await;
+        let client = Client::new(m.uri(), m.token().to_string());
+        client.set_cache_capacity(Some(2));
+
+        let _: Vec<Task> = client
+            .tasks(&[100, 200, 300])
+            .query()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(1, client.cache_stats().evictions);
+
+        let cached: Vec<_> = [100, 200, 300]
+            .iter()
+            .filter_map(|id| client.cached_task(*id))
+            .collect();
+        assert_eq!(2, cached.len());
+
+        client.set_cache_capacity(Some(1));
+        assert_eq!(2, client.cache_stats().evictions);
+    }
+
+    #[tokio::test]
+    async fn cache_stats() {
+        let m = setup().await;
+        let client = Client::new(m.uri(), m.token().to_string());
+
+        let _: Vec<Task> = client.tasks(&[100]).query().try_collect().await.unwrap();
+        let _: Vec<Task> = client.tasks(&[100]).query().try_collect().await.unwrap();
+
+        let stats = client.cache_stats();
+        assert_eq!(1, stats.hits);
+        assert_eq!(1, stats.misses);
+    }
+
+    #[tokio::test]
+    async fn uncached() {
+        let m = setup().await;
+        let client = Client::new(m.uri(), m.token().to_string());
+
+        for n in 1..=2 {
+            let tasks: Vec<Task> = client
+                .tasks(&[100])
+                .projects()
+                .uncached()
+                .query()
+                .try_collect()
+                .await
+                .unwrap();
+            assert_eq!(1, tasks.len());
+            assert_eq!(1, tasks[0].projects().await.unwrap().len());
+            // Both the tasks and the projects are requested every time
+            assert_eq!(n * 2, m.n_requests().await);
+        }
+
+        assert!(client.cached_task(100).is_none());
+    }
+
     #[tokio::test]
     async fn projects_on_demand() {
         let m = setup().await;
@@ -238,6 +386,40 @@ mod test {
         assert_eq!(10, project.id());
     }
 
+    #[tokio::test]
+    async fn projects_uncached() {
+        let m = setup().await;
+        let client = Client::new(m.uri(), m.token().to_string());
+
+        let task: Task = client
+            .tasks(&[100])
+            .uncached()
+            .query()
+            .try_next()
+            .await
+            .unwrap()
+            .unwrap();
+        let projects = task.projects().await.unwrap();
+        assert_eq!(10, projects[0].id());
+        // Resolved on the task itself, even though it's not the cached one
+        let requests = m.n_requests().await;
+        assert_eq!(1, task.projects().await.unwrap().len());
+        assert_eq!(requests, m.n_requests().await);
+
+        // Evicted from the cache
+        let task: Task = client
+            .tasks(&[100])
+            .query()
+            .try_next()
+            .await
+            .unwrap()
+            .unwrap();
+        client.set_cache_capacity(Some(1));
+        let _other: Vec<Task> = client.tasks(&[200]).query().try_collect().await.unwrap();
+        assert!(client.cached_task(100).is_none());
+        assert_eq!(10, task.projects().await.unwrap()[0].id());
+    }
+
     #[tokio::test]
     async fn projects_batch() {
         let m = setup().await;
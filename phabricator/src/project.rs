@@ -1,3 +1,5 @@
+#[cfg(feature = "serde")]
+use crate::snapshot::ProjectSnapshot;
 use crate::{Client, WeakClient};
 use phabricator_api::project::search::SearchData;
 use phabricator_api::types::Phid;
@@ -21,7 +23,7 @@ struct Inner {
 }
 
 impl Project {
-    fn from_searchdata(data: SearchData, client: &Client) -> Project {
+    pub(crate) fn from_searchdata(data: SearchData, client: &Client) -> Project {
         let inner = Arc::new(Mutex::new(Inner {
             title: data.fields.name,
             slug: data.fields.slug,
@@ -36,8 +38,11 @@ impl Project {
         }
     }
 
-    fn update_searchdata(&mut self, _data: SearchData) {
-        todo!()
+    fn update_searchdata(&mut self, data: SearchData) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.title = data.fields.name;
+        inner.slug = data.fields.slug;
+        inner.description = data.fields.description;
     }
 
     pub(crate) fn update_from_searchdata(data: SearchData, client: &Client) -> Project {
@@ -55,6 +60,10 @@ impl Project {
         self.id
     }
 
+    pub fn phid(&self) -> &Phid {
+        &self.phid
+    }
+
     pub fn title(&self) -> String {
         let l = self.inner.lock().unwrap();
         l.title.clone()
@@ -70,3 +79,39 @@ impl Project {
         l.description.clone()
     }
 }
+
+#[cfg(feature = "serde")]
+impl Project {
+    pub(crate) fn from_snapshot(snapshot: ProjectSnapshot, client: &Client) -> Project {
+        let inner = Arc::new(Mutex::new(Inner {
+            title: snapshot.title,
+            slug: snapshot.slug,
+            description: snapshot.description,
+        }));
+
+        Project {
+            id: snapshot.id,
+            phid: snapshot.phid,
+            client: client.downgrade(),
+            inner,
+        }
+    }
+
+    pub(crate) fn update_snapshot(&mut self, snapshot: ProjectSnapshot) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.title = snapshot.title;
+        inner.slug = snapshot.slug;
+        inner.description = snapshot.description;
+    }
+
+    pub fn snapshot(&self) -> ProjectSnapshot {
+        let l = self.inner.lock().unwrap();
+        ProjectSnapshot {
+            id: self.id,
+            phid: self.phid.clone(),
+            title: l.title.clone(),
+            slug: l.slug.clone(),
+            description: l.description.clone(),
+        }
+    }
+}
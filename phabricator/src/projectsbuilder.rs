@@ -10,10 +10,11 @@ use phabricator_api::types::Phid;
 use phabricator_api::RequestError;
 use std::sync::Arc;
 
-async fn get(
+pub(crate) async fn get(
     client: &Client,
     search: Arc<Search>,
     cursor: Option<Cursor>,
+    cache: bool,
 ) -> Result<search::QueryData<Project>, RequestError> {
     let mut data = match cursor {
         Some(cursor) => {
@@ -29,7 +30,13 @@ async fn get(
     let projects = data
         .data
         .drain(..)
-        .map(|d| Project::update_from_searchdata(d, client))
+        .map(|d| {
+            if cache {
+                Project::update_from_searchdata(d, client)
+            } else {
+                Project::from_searchdata(d, client)
+            }
+        })
         .collect();
 
     let cursor = if data.cursor.after.is_some() {
@@ -44,6 +51,7 @@ async fn get(
 pub struct ProjectsBuilder<'c, P> {
     client: &'c Client,
     phids: P,
+    cache: bool,
 }
 
 impl<'a, 'c, P> ProjectsBuilder<'c, P>
@@ -51,16 +59,28 @@ where
     P: IntoIterator<Item = &'a Phid>,
 {
     pub(crate) fn new(client: &'c Client, phids: P) -> Self {
-        ProjectsBuilder { client, phids }
+        ProjectsBuilder {
+            client,
+            phids,
+            cache: true,
+        }
+    }
+
+    /// Always retrieve the projects from the server and don't store them in
+    /// the cache
+    pub fn uncached(mut self) -> Self {
+        self.cache = false;
+        self
     }
 
     pub fn query(self) -> impl Stream<Item = Result<Project, RequestError>> + 'c {
         let client = &self.client;
+        let cache = self.cache;
         let (lookup, cached) =
             self.phids
                 .into_iter()
                 .fold((vec![], vec![]), |(mut lookup, mut cached), p| {
-                    let project = client.cached_project(p);
+                    let project = cache.then(|| client.cached_project(p)).flatten();
 
                     match project {
                         Some(prj) => cached.push(prj),
@@ -81,7 +101,7 @@ where
             self.client,
             cached,
             search,
-            Box::new(|client, search, cursor| get(client, search, cursor).boxed()),
+            Box::new(move |client, search, cursor| get(client, search, cursor, cache).boxed()),
         )
     }
 }
@@ -0,0 +1,143 @@
+use crate::remarkup;
+use phabricator_api::phid::Item;
+use phabricator_api::types::Phid;
+
+/// Object referenced from a task, e.g. another task or a revision
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reference {
+    /// Monogram of the object, e.g. `D12`
+    pub name: String,
+    pub phid: Phid,
+    /// Object type, e.g. `TASK` or `DREV`
+    pub ty: String,
+    pub type_name: String,
+    pub title: String,
+    pub uri: String,
+    pub closed: bool,
+}
+
+impl From<Item> for Reference {
+    fn from(item: Item) -> Self {
+        Reference {
+            name: item.name,
+            phid: item.phid,
+            ty: item.ty,
+            type_name: item.type_name,
+            title: item.full_name,
+            uri: item.uri,
+            closed: item.status == "closed",
+        }
+    }
+}
+
+fn is_phid(word: &str) -> bool {
+    match word.strip_prefix("PHID-").and_then(|w| w.split_once('-')) {
+        Some((ty, id)) => {
+            ty.len() == 4 && ty.chars().all(|c| c.is_ascii_uppercase()) && !id.is_empty()
+        }
+        None => false,
+    }
+}
+
+/// Names of the objects referenced in Remarkup `text` that can be passed to
+/// phid.lookup: monograms, outside of code, and raw phids
+pub(crate) fn scan(text: &str) -> Vec<String> {
+    let doc = remarkup::parse(text);
+    let mut names: Vec<String> = doc.references().iter().map(|r| r.monogram()).collect();
+    for word in text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '-')) {
+        if is_phid(word) && !names.iter().any(|n| n == word) {
+            names.push(word.to_string());
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "reqwest")]
+    use crate::{Client, Task};
+    #[cfg(feature = "reqwest")]
+    use futures::prelude::*;
+    #[cfg(feature = "reqwest")]
+    use phabricator_mock::PhabMockServer;
+
+    #[test]
+    fn scanning() {
+        assert_eq!(
+            vec!["T12", "D3", "PHID-TASK-abc", "PHID-XACT-TASK-def"],
+            scan(
+                "See T12, {D3} and T12#4 but not `T5`.\n\n\
+                 Related: PHID-TASK-abc (PHID-XACT-TASK-def) PHID-task PHID-TASK-"
+            )
+        );
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn references() {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        let other = m.new_simple_task(200, &user);
+        let closed = phabricator_mock::task()
+            .id(300)
+            .full_name("Closed task")
+            .description("Done")
+            .author(user.clone())
+            .status(
+                phabricator_mock::status()
+                    .value("resolved")
+                    .name("Resolved")
+                    .closed(true)
+                    .build()
+                    .unwrap(),
+            )
+            .priority(m.default_priority())
+            .build()
+            .unwrap();
+        m.add_task(closed);
+        let t = phabricator_mock::task()
+            .id(100)
+            .full_name("Task")
+            .description(format!(
+                "Blocked on T200, see D45 and {}.\n\n- [x] step\n- [ ] other step",
+                other.phid
+            ))
+            .author(user.clone())
+            .status(m.default_status())
+            .priority(m.default_priority())
+            .build()
+            .unwrap();
+        m.add_task(t.clone());
+        t.add_comment(&user, "Fixed by T300, unlike T100 itself");
+
+        let client = Client::new(m.uri(), m.token().to_string());
+        let task: Task = client
+            .tasks(&[100])
+            .query()
+            .try_next()
+            .await
+            .unwrap()
+            .unwrap();
+
+        let comments = task.comments().await.unwrap();
+        assert_eq!(1, comments.len());
+        assert_eq!("Fixed by T300, unlike T100 itself", comments[0].content);
+
+        let refs = task.references().await.unwrap();
+        // D45 doesn't exist and T200 is referenced twice
+        assert_eq!(
+            vec![("T200", "Task T200"), ("T300", "Closed task")],
+            refs.iter()
+                .map(|r| (r.name.as_str(), r.title.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(other.phid.to_string(), refs[0].phid.0);
+        assert!(!refs[0].closed);
+        assert!(refs[1].closed);
+
+        let checklist = task.checklist();
+        assert_eq!(1, checklist.done());
+        assert_eq!(Some(0.5), checklist.ratio());
+    }
+}
@@ -0,0 +1,344 @@
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag};
+
+/// Convert CommonMark (with tables, task lists and strikethrough) to
+/// Remarkup. Remarkup has no escaping, so text that happens to contain
+/// Remarkup markup is passed on as is.
+pub fn from_markdown(markdown: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+
+    let mut converter = Converter {
+        buffers: vec![String::new()],
+        lists: Vec::new(),
+        rows: Vec::new(),
+        header: false,
+        code: false,
+    };
+    for event in Parser::new_ext(markdown, options) {
+        converter.event(event);
+    }
+
+    converter.buffers[0].trim_end().to_string()
+}
+
+struct Converter {
+    // Output, with a nested buffer per open block or link
+    buffers: Vec<String>,
+    // Open lists and whether they're ordered
+    lists: Vec<bool>,
+    rows: Vec<Vec<String>>,
+    // Whether the first row in rows is a header
+    header: bool,
+    code: bool,
+}
+
+impl Converter {
+    fn out(&mut self) -> &mut String {
+        self.buffers.last_mut().unwrap()
+    }
+
+    fn push(&mut self) {
+        self.buffers.push(String::new());
+    }
+
+    fn pop(&mut self) -> String {
+        self.buffers.pop().unwrap()
+    }
+
+    // Finish a block with an empty line, unless inside a list item where
+    // that would end the list
+    fn end_block(&mut self) {
+        if self.lists.is_empty() {
+            self.out().push_str("\n\n");
+        } else {
+            self.out().push('\n');
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(t) => self.out().push_str(&t),
+            Event::Html(h) => self.out().push_str(&h),
+            Event::Code(c) => {
+                let code = if c.contains('`') {
+                    format!("##{}##", c)
+                } else {
+                    format!("`{}`", c)
+                };
+                self.out().push_str(&code)
+            }
+            Event::FootnoteReference(f) => {
+                let footnote = format!("[^{}]", f);
+                self.out().push_str(&footnote)
+            }
+            // Soft breaks are only layout in Markdown, but Remarkup keeps
+            // line breaks
+            Event::SoftBreak => {
+                let c = if self.code { '\n' } else { ' ' };
+                self.out().push(c)
+            }
+            Event::HardBreak => self.out().push('\n'),
+            Event::Rule => {
+                self.out().push_str("---");
+                self.end_block();
+            }
+            Event::TaskListMarker(checked) => {
+                let marker = if checked { "[x] " } else { "[ ] " };
+                self.out().push_str(marker)
+            }
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading(..)
+            | Tag::BlockQuote
+            | Tag::Item
+            | Tag::TableCell
+            | Tag::Link(..)
+            | Tag::Image(..)
+            | Tag::FootnoteDefinition(_) => self.push(),
+            Tag::CodeBlock(_) => {
+                self.code = true;
+                self.push()
+            }
+            Tag::List(start) => {
+                // Nested lists start on their own line in the item
+                let out = self.out();
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                self.lists.push(start.is_some());
+            }
+            Tag::Table(_) => {
+                self.rows.clear();
+                self.header = false;
+            }
+            Tag::TableHead => {
+                self.header = true;
+                self.rows.push(Vec::new());
+            }
+            Tag::TableRow => self.rows.push(Vec::new()),
+            Tag::Emphasis => self.out().push_str("//"),
+            Tag::Strong => self.out().push_str("**"),
+            Tag::Strikethrough => self.out().push_str("~~"),
+            Tag::Paragraph => (),
+        }
+    }
+
+    fn end(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => self.end_block(),
+            Tag::Heading(level, ..) => {
+                let text = self.pop();
+                let marker = "=".repeat(level as usize);
+                let header = format!("{} {} {}", marker, text.trim(), marker);
+                self.out().push_str(&header);
+                self.end_block();
+            }
+            Tag::BlockQuote => {
+                let quoted: String = self
+                    .pop()
+                    .trim_end()
+                    .lines()
+                    .map(|l| {
+                        if l.is_empty() {
+                            ">\n".to_string()
+                        } else {
+                            format!("> {}\n", l)
+                        }
+                    })
+                    .collect();
+                self.out().push_str(quoted.trim_end());
+                self.end_block();
+            }
+            Tag::CodeBlock(kind) => {
+                self.code = false;
+                let code = self.pop();
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().map(str::to_string)
+                    }
+                    CodeBlockKind::Indented => None,
+                };
+
+                let block = if code.contains("```") {
+                    // Can't be fenced, so fall back to an indented block
+                    let indented: Vec<_> = code.lines().map(|l| format!("  {}", l)).collect();
+                    match language {
+                        Some(language) => format!("  lang={}\n{}", language, indented.join("\n")),
+                        None => indented.join("\n"),
+                    }
+                } else {
+                    match language {
+                        Some(language) => format!("```lang={}\n{}```", language, code),
+                        None => format!("```\n{}```", code),
+                    }
+                };
+                self.out().push_str(&block);
+                self.end_block();
+            }
+            Tag::List(_) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.out().push('\n');
+                }
+            }
+            Tag::Item => {
+                let content = self.pop();
+                let marker = if *self.lists.last().unwrap() {
+                    "# "
+                } else {
+                    "- "
+                };
+                // Blank lines would end the list in Remarkup
+                let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+                let mut item = format!("{}{}\n", marker, lines.next().unwrap_or_default());
+                for line in lines {
+                    item.push_str("  ");
+                    item.push_str(line);
+                    item.push('\n');
+                }
+                self.out().push_str(&item);
+            }
+            Tag::FootnoteDefinition(name) => {
+                let content = self.pop();
+                let footnote = format!("[^{}]: {}", name, content.trim());
+                self.out().push_str(&footnote);
+                self.end_block();
+            }
+            Tag::Table(_) => {
+                let mut table = String::new();
+                for (i, row) in self.rows.iter().enumerate() {
+                    table.push_str(&format!("| {} |\n", row.join(" | ")));
+                    if i == 0 && self.header {
+                        table.push_str(&format!("|{}\n", " --- |".repeat(row.len())));
+                    }
+                }
+                self.out().push_str(table.trim_end());
+                self.end_block();
+            }
+            Tag::TableCell => {
+                let cell = self.pop();
+                self.rows.last_mut().unwrap().push(cell.trim().to_string());
+            }
+            Tag::TableHead | Tag::TableRow => (),
+            Tag::Emphasis => self.out().push_str("//"),
+            Tag::Strong => self.out().push_str("**"),
+            Tag::Strikethrough => self.out().push_str("~~"),
+            Tag::Link(_, url, _) | Tag::Image(_, url, _) => {
+                let text = self.pop();
+                let link = if text.is_empty() || *text == *url {
+                    if url.starts_with("http://") || url.starts_with("https://") {
+                        url.to_string()
+                    } else {
+                        format!("[[ {} ]]", url)
+                    }
+                } else {
+                    format!("[[ {} | {} ]]", url, text)
+                };
+                self.out().push_str(&link);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::remarkup::{parse, Block};
+
+    #[test]
+    fn blocks() {
+        let markdown = "# Title\n\n\
+                        Some *emphasis*, **strong** and ~~gone~~ text\nwrapped, with `code`.\\\n\
+                        Hard break.\n\n\
+                        Sub\n---\n\n\
+                        > quote\n>\n> # inside\n\n\
+                        ***\n";
+        let expected = "= Title =\n\n\
+                        Some //emphasis//, **strong** and ~~gone~~ text wrapped, with `code`.\n\
+                        Hard break.\n\n\
+                        == Sub ==\n\n\
+                        > quote\n>\n> = inside =\n\n\
+                        ---";
+        assert_eq!(expected, from_markdown(markdown));
+    }
+
+    #[test]
+    fn code() {
+        assert_eq!(
+            "```lang=rust\nfn main() {}\n```\n\n```\nplain\n```\n\n```\nindented\n```",
+            from_markdown("```rust ignore\nfn main() {}\n```\n\n```\nplain\n```\n\n    indented\n")
+        );
+        assert_eq!("##a`b##", from_markdown("``a`b``"));
+
+        let converted = from_markdown("````md\n```\n````");
+        assert_eq!("  lang=md\n  ```", converted);
+        assert_eq!(
+            vec![Block::Code {
+                language: Some("md".to_string()),
+                code: "```".to_string()
+            }],
+            parse(&converted).blocks
+        );
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(
+            "See [[ https://example.com | the site ]], https://example.com and [[ /T1 ]] or [[ https://x.org/a.png | alt ]]",
+            from_markdown(
+                "See [the site](https://example.com), <https://example.com> and [/T1](/T1) or ![alt](https://x.org/a.png)"
+            )
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let markdown = "- [ ] todo\n- [x] done\n  1. first\n  2. second\n     - deep\n\n\
+                        Between\n\n\
+                        1. loose\n\n   continued\n2. next\n";
+        let expected = "- [ ] todo\n\
+                        - [x] done\n  # first\n  # second\n    - deep\n\n\
+                        Between\n\n\
+                        # loose\n  continued\n# next";
+        let converted = from_markdown(markdown);
+        assert_eq!(expected, converted);
+
+        let doc = parse(&converted);
+        let checklist: Vec<_> = doc
+            .checklist()
+            .iter()
+            .map(|i| (i.checked, i.text()))
+            .collect();
+        assert_eq!(
+            vec![
+                (Some(false), "todo".to_string()),
+                (Some(true), "done".to_string())
+            ],
+            checklist
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let markdown = "| A | *B* |\n|---|:-:|\n| 1 | [x](/y) |\n| 2 | |\n";
+        let converted = from_markdown(markdown);
+        assert_eq!(
+            "| A | //B// |\n| --- | --- |\n| 1 | [[ /y | x ]] |\n| 2 |  |",
+            converted
+        );
+        match &parse(&converted).blocks[0] {
+            Block::Table(t) => {
+                assert!(t.header.is_some());
+                assert_eq!(2, t.rows.len());
+            }
+            b => panic!("Not a table: {:?}", b),
+        }
+    }
+}
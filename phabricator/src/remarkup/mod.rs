@@ -0,0 +1,247 @@
+mod parse;
+pub use parse::parse;
+mod render;
+pub use render::Renderer;
+mod markdown;
+pub use markdown::from_markdown;
+mod process;
+pub use phabricator_api::remarkup::process::Context;
+
+/// Parsed Remarkup document
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Document {
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Block {
+    Header {
+        level: u8,
+        content: Vec<Inline>,
+    },
+    Paragraph(Vec<Inline>),
+    List {
+        ordered: bool,
+        items: Vec<ListItem>,
+    },
+    Code {
+        language: Option<String>,
+        code: String,
+    },
+    Quote(Vec<Block>),
+    Table(Table),
+    Rule,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ListItem {
+    /// `Some` for checklist items, with whether it's checked
+    pub checked: Option<bool>,
+    pub content: Vec<Inline>,
+    /// Nested lists
+    pub children: Vec<Block>,
+}
+
+impl ListItem {
+    pub fn text(&self) -> String {
+        text(&self.content)
+    }
+}
+
+pub type Cell = Vec<Inline>;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Table {
+    pub header: Option<Vec<Cell>>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Inline {
+    Text(String),
+    Bold(Vec<Inline>),
+    Italic(Vec<Inline>),
+    Deleted(Vec<Inline>),
+    Underline(Vec<Inline>),
+    Highlight(Vec<Inline>),
+    Monospace(String),
+    Link {
+        target: String,
+        text: Option<String>,
+    },
+    /// Plain object reference, e.g. `T123`
+    Reference(ObjectRef),
+    /// Embedded object, e.g. `{F12}`
+    Embed(ObjectRef),
+    Mention(String),
+    Hashtag(String),
+    LineBreak,
+}
+
+/// Reference to an object by its monogram, e.g. `T123` or `D45#2`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ObjectRef {
+    /// Object type prefix, e.g. `T` for tasks
+    pub prefix: char,
+    pub id: u32,
+    pub anchor: Option<String>,
+}
+
+impl ObjectRef {
+    /// Monogram of the object, e.g. `T123`
+    pub fn monogram(&self) -> String {
+        format!("{}{}", self.prefix, self.id)
+    }
+}
+
+impl std::fmt::Display for ObjectRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.prefix, self.id)?;
+        if let Some(anchor) = &self.anchor {
+            write!(f, "#{}", anchor)?;
+        }
+        Ok(())
+    }
+}
+
+/// Text content of inlines with all markup stripped
+pub fn text(inlines: &[Inline]) -> String {
+    let mut out = String::new();
+    for i in inlines {
+        match i {
+            Inline::Text(t) | Inline::Monospace(t) => out.push_str(t),
+            Inline::Bold(c)
+            | Inline::Italic(c)
+            | Inline::Deleted(c)
+            | Inline::Underline(c)
+            | Inline::Highlight(c) => out.push_str(&text(c)),
+            Inline::Link { target, text } => out.push_str(text.as_ref().unwrap_or(target)),
+            Inline::Reference(r) => out.push_str(&r.to_string()),
+            Inline::Embed(r) => out.push_str(&format!("{{{}}}", r)),
+            Inline::Mention(m) => {
+                out.push('@');
+                out.push_str(m)
+            }
+            Inline::Hashtag(h) => {
+                out.push('#');
+                out.push_str(h)
+            }
+            Inline::LineBreak => out.push('\n'),
+        }
+    }
+    out
+}
+
+fn walk_inlines<'a>(inlines: &'a [Inline], f: &mut dyn FnMut(&'a Inline)) {
+    for i in inlines {
+        f(i);
+        match i {
+            Inline::Bold(c)
+            | Inline::Italic(c)
+            | Inline::Deleted(c)
+            | Inline::Underline(c)
+            | Inline::Highlight(c) => walk_inlines(c, f),
+            _ => (),
+        }
+    }
+}
+
+// Inline containers of the blocks in document order
+fn walk_containers<'a>(blocks: &'a [Block], f: &mut dyn FnMut(&'a [Inline])) {
+    for b in blocks {
+        match b {
+            Block::Header { content, .. } | Block::Paragraph(content) => f(content),
+            Block::List { items, .. } => {
+                for item in items {
+                    f(&item.content);
+                    walk_containers(&item.children, f);
+                }
+            }
+            Block::Quote(blocks) => walk_containers(blocks, f),
+            Block::Table(table) => {
+                for cell in table.header.iter().chain(table.rows.iter()).flatten() {
+                    f(cell)
+                }
+            }
+            Block::Code { .. } | Block::Rule => (),
+        }
+    }
+}
+
+fn walk_items<'a>(blocks: &'a [Block], f: &mut dyn FnMut(&'a ListItem)) {
+    for b in blocks {
+        match b {
+            Block::List { items, .. } => {
+                for item in items {
+                    f(item);
+                    walk_items(&item.children, f);
+                }
+            }
+            Block::Quote(blocks) => walk_items(blocks, f),
+            _ => (),
+        }
+    }
+}
+
+impl Document {
+    /// All inlines in the document, including nested ones
+    pub fn inlines(&self) -> Vec<&Inline> {
+        let mut inlines = Vec::new();
+        walk_containers(&self.blocks, &mut |c| {
+            walk_inlines(c, &mut |i| inlines.push(i))
+        });
+        inlines
+    }
+
+    /// Referenced and embedded objects in order of appearance, without
+    /// duplicates
+    pub fn references(&self) -> Vec<&ObjectRef> {
+        let mut refs: Vec<&ObjectRef> = Vec::new();
+        for i in self.inlines() {
+            if let Inline::Reference(r) | Inline::Embed(r) = i {
+                if !refs
+                    .iter()
+                    .any(|seen| seen.prefix == r.prefix && seen.id == r.id)
+                {
+                    refs.push(r);
+                }
+            }
+        }
+        refs
+    }
+
+    pub fn mentions(&self) -> Vec<&str> {
+        let mut mentions = Vec::new();
+        for i in self.inlines() {
+            if let Inline::Mention(m) = i {
+                if !mentions.contains(&m.as_str()) {
+                    mentions.push(m.as_str());
+                }
+            }
+        }
+        mentions
+    }
+
+    pub fn hashtags(&self) -> Vec<&str> {
+        let mut tags = Vec::new();
+        for i in self.inlines() {
+            if let Inline::Hashtag(h) = i {
+                if !tags.contains(&h.as_str()) {
+                    tags.push(h.as_str());
+                }
+            }
+        }
+        tags
+    }
+
+    /// All checklist items, including nested ones
+    pub fn checklist(&self) -> Vec<&ListItem> {
+        let mut items = Vec::new();
+        walk_items(&self.blocks, &mut |item| {
+            if item.checked.is_some() {
+                items.push(item)
+            }
+        });
+        items
+    }
+}
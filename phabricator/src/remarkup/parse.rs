@@ -0,0 +1,983 @@
+use super::{Block, Document, Inline, ListItem, ObjectRef, Table};
+
+// Object types that can be referenced by their monogram
+const MONOGRAMS: &str = "CDEFLMPQTVWZ";
+
+pub fn parse(text: &str) -> Document {
+    let lines: Vec<&str> = text.lines().collect();
+    Document {
+        blocks: blocks(&lines),
+    }
+}
+
+fn blocks(lines: &[&str]) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        if line.trim().is_empty() {
+            i += 1;
+            continue;
+        }
+
+        let (block, consumed) = if line.trim_start().starts_with("```") {
+            fence(&lines[i..])
+        } else if list_marker(line).is_some() {
+            list(&lines[i..])
+        } else if line.starts_with("  ") || line.starts_with('\t') {
+            indented_code(&lines[i..])
+        } else if let Some(block) = header(line) {
+            (block, 1)
+        } else if let Some(level) = lines.get(i + 1).and_then(|l| underline(l)) {
+            (
+                Block::Header {
+                    level,
+                    content: inlines(line.trim()),
+                },
+                2,
+            )
+        } else if is_rule(line) {
+            (Block::Rule, 1)
+        } else if line.starts_with('>') {
+            quote(&lines[i..])
+        } else if line.trim_start().starts_with('|') {
+            table(&lines[i..])
+        } else {
+            paragraph(&lines[i..])
+        };
+
+        blocks.push(block);
+        i += consumed;
+    }
+
+    blocks
+}
+
+fn starts_block(line: &str) -> bool {
+    line.trim_start().starts_with("```")
+        || list_marker(line).is_some()
+        || header(line).is_some()
+        || is_rule(line)
+        || line.starts_with('>')
+        || line.trim_start().starts_with('|')
+}
+
+fn header(line: &str) -> Option<Block> {
+    let line = line.trim();
+    let level = line.chars().take_while(|&c| c == '=').count();
+    if level == 0 {
+        return None;
+    }
+    let content = line.trim_matches('=').trim();
+    if content.is_empty() {
+        return None;
+    }
+    Some(Block::Header {
+        level: level.min(6) as u8,
+        content: inlines(content),
+    })
+}
+
+// Level of a `====` or `----` line underlining a header
+fn underline(line: &str) -> Option<u8> {
+    let line = line.trim();
+    if !line.is_empty() && line.chars().all(|c| c == '=') {
+        Some(1)
+    } else if line.len() >= 3 && line.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+fn is_rule(line: &str) -> bool {
+    let line = line.trim();
+    line.len() >= 3 && line.chars().all(|c| c == '-')
+}
+
+// `lang=rust, name=main.rs` style code block options, None if the line
+// isn't one. Returns the language if given.
+fn code_options(line: &str) -> Option<Option<String>> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    let mut language = None;
+    for option in line.split(',') {
+        match option.trim().split_once('=') {
+            Some(("lang", lang)) => language = Some(lang.trim().to_string()),
+            Some((key, _)) if ["name", "lines", "counterexample"].contains(&key.trim()) => (),
+            None if option.trim() == "counterexample" => (),
+            _ => return None,
+        }
+    }
+    Some(language)
+}
+
+fn fence(lines: &[&str]) -> (Block, usize) {
+    let first = &lines[0].trim_start()[3..];
+    if let Some(end) = first.find("```") {
+        let code = first[..end].to_string();
+        return (
+            Block::Code {
+                language: None,
+                code,
+            },
+            1,
+        );
+    }
+
+    let mut language = None;
+    let mut code = Vec::new();
+    if let Some(options) = code_options(first) {
+        language = options;
+    } else if !first.trim().is_empty() {
+        let word = first.trim();
+        if word
+            .chars()
+            .all(|c| c.is_alphanumeric() || "+-#_".contains(c))
+        {
+            // Markdown style language
+            language = Some(word.to_string());
+        } else {
+            code.push(first);
+        }
+    }
+
+    let mut i = 1;
+    let mut closed = false;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        if let Some(end) = line.find("```") {
+            if !line[..end].trim().is_empty() {
+                code.push(&line[..end]);
+            }
+            closed = true;
+            break;
+        }
+        if code.is_empty() && language.is_none() {
+            if let Some(options) = code_options(line) {
+                language = options;
+                continue;
+            }
+        }
+        code.push(line);
+    }
+    if !closed {
+        i = lines.len();
+    }
+
+    (
+        Block::Code {
+            language,
+            code: code.join("\n"),
+        },
+        i,
+    )
+}
+
+// Length of the leading spaces and tabs, up to `max`; these are single
+// bytes so the result is always a char boundary
+fn leading_indent(line: &str, max: usize) -> usize {
+    line.bytes()
+        .take(max)
+        .take_while(|b| *b == b' ' || *b == b'\t')
+        .count()
+}
+
+fn indented_code(lines: &[&str]) -> (Block, usize) {
+    let indented = |l: &str| l.starts_with("  ") || l.starts_with('\t');
+    let mut end = 0;
+    let mut i = 0;
+    while i < lines.len() {
+        if indented(lines[i]) {
+            i += 1;
+            end = i;
+        } else if lines[i].trim().is_empty() {
+            // Blank lines are only part of the block if it continues after
+            i += 1;
+        } else {
+            break;
+        }
+    }
+
+    let block = &lines[..end];
+    let indent = block
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| leading_indent(l, usize::MAX))
+        .min()
+        .unwrap_or(0);
+    let mut code: Vec<&str> = block
+        .iter()
+        .map(|l| &l[leading_indent(l, indent)..])
+        .collect();
+
+    let mut language = None;
+    if let Some(options) = code.first().and_then(|l| code_options(l)) {
+        language = options;
+        code.remove(0);
+    }
+
+    (
+        Block::Code {
+            language,
+            code: code.join("\n"),
+        },
+        end,
+    )
+}
+
+fn quote(lines: &[&str]) -> (Block, usize) {
+    let quoted: Vec<&str> = lines
+        .iter()
+        .take_while(|l| l.starts_with('>'))
+        .map(|l| {
+            let l = &l[1..];
+            l.strip_prefix(' ').unwrap_or(l)
+        })
+        .collect();
+    let consumed = quoted.len();
+    (Block::Quote(blocks(&quoted)), consumed)
+}
+
+// Split a table row on `|`, except in links and monospace
+fn split_cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut in_link = false;
+    let mut in_mono = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '[' if !in_mono && chars.peek() == Some(&'[') => in_link = true,
+            ']' if !in_mono && chars.peek() == Some(&']') => in_link = false,
+            '`' => in_mono = !in_mono,
+            '|' if !in_link && !in_mono => {
+                cells.push(cell.trim().to_string());
+                cell.clear();
+                continue;
+            }
+            _ => (),
+        }
+        cell.push(c);
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+fn table(lines: &[&str]) -> (Block, usize) {
+    let rows: Vec<Vec<String>> = lines
+        .iter()
+        .take_while(|l| l.trim_start().starts_with('|'))
+        .map(|l| split_cells(l))
+        .collect();
+    let consumed = rows.len();
+
+    let separator = |row: &Vec<String>| {
+        row.iter().all(|c| {
+            let c = c.trim_matches(':');
+            !c.is_empty() && c.chars().all(|c| c == '-')
+        })
+    };
+    let parse_row = |row: &Vec<String>| row.iter().map(|c| inlines(c)).collect::<Vec<_>>();
+
+    let mut table = Table::default();
+    let mut rows = &rows[..];
+    if rows.len() > 1 && separator(&rows[1]) {
+        table.header = Some(parse_row(&rows[0]));
+        rows = &rows[1..];
+    }
+    table.rows = rows
+        .iter()
+        .filter(|r| !separator(r))
+        .map(parse_row)
+        .collect();
+
+    (Block::Table(table), consumed)
+}
+
+struct Marker<'a> {
+    depth: usize,
+    ordered: bool,
+    rest: &'a str,
+}
+
+fn list_marker(line: &str) -> Option<Marker<'_>> {
+    let trimmed = line.trim_start();
+    let indent = line.len() - trimmed.len();
+    let first = trimmed.chars().next()?;
+
+    let (count, len, ordered) = match first {
+        '-' | '*' | '#' => {
+            let count = trimmed.chars().take_while(|&c| c == first).count();
+            (count, count, first == '#')
+        }
+        '0'..='9' => {
+            let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+            match trimmed[digits..].chars().next() {
+                Some('.') | Some(')') => (1, digits + 1, true),
+                _ => return None,
+            }
+        }
+        '[' => {
+            // Checklist item without a list marker
+            checkbox(trimmed)?;
+            return Some(Marker {
+                depth: indent / 2 + 1,
+                ordered: false,
+                rest: trimmed,
+            });
+        }
+        _ => return None,
+    };
+
+    let rest = trimmed[len..].strip_prefix(' ')?;
+    Some(Marker {
+        depth: indent / 2 + count,
+        ordered,
+        rest: rest.trim_start(),
+    })
+}
+
+// Checkbox at the start of a list item and the remaining text
+fn checkbox(text: &str) -> Option<(bool, &str)> {
+    let (checked, rest) = if let Some(rest) = text.strip_prefix("[ ]") {
+        (false, rest)
+    } else if let Some(rest) = text.strip_prefix("[]") {
+        (false, rest)
+    } else if let Some(rest) = text
+        .strip_prefix("[x]")
+        .or_else(|| text.strip_prefix("[X]"))
+    {
+        (true, rest)
+    } else {
+        return None;
+    };
+
+    if rest.is_empty() || rest.starts_with(' ') {
+        Some((checked, rest.trim_start()))
+    } else {
+        None
+    }
+}
+
+struct Entry {
+    depth: usize,
+    ordered: bool,
+    text: String,
+}
+
+fn list(lines: &[&str]) -> (Block, usize) {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut consumed = 0;
+
+    for line in lines {
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some(marker) = list_marker(line) {
+            entries.push(Entry {
+                depth: marker.depth,
+                ordered: marker.ordered,
+                text: marker.rest.to_string(),
+            });
+        } else if starts_block(line) {
+            break;
+        } else {
+            // Continuation of the previous item
+            let last = entries.last_mut().unwrap();
+            last.text.push('\n');
+            last.text.push_str(line.trim());
+        }
+        consumed += 1;
+    }
+
+    // Nest from the shallowest entry, so entries shallower than the first
+    // one still end up in the list
+    let mut i = 0;
+    let depth = entries.iter().map(|e| e.depth).min().unwrap_or(1);
+    (nest(&entries, &mut i, depth), consumed)
+}
+
+fn nest(entries: &[Entry], i: &mut usize, depth: usize) -> Block {
+    let ordered = entries[*i].ordered;
+    let mut items: Vec<ListItem> = Vec::new();
+
+    while *i < entries.len() && entries[*i].depth >= depth {
+        let entry = &entries[*i];
+        if entry.depth > depth {
+            let nested = nest(entries, i, entry.depth);
+            match items.last_mut() {
+                Some(item) => item.children.push(nested),
+                None => items.push(ListItem {
+                    children: vec![nested],
+                    ..Default::default()
+                }),
+            }
+        } else {
+            let (checked, text) = match checkbox(&entry.text) {
+                Some((checked, text)) => (Some(checked), text),
+                None => (None, entry.text.as_str()),
+            };
+            items.push(ListItem {
+                checked,
+                content: inlines(text),
+                children: Vec::new(),
+            });
+            *i += 1;
+        }
+    }
+
+    Block::List { ordered, items }
+}
+
+fn paragraph(lines: &[&str]) -> (Block, usize) {
+    let mut end = 1;
+    while end < lines.len() {
+        let line = lines[end];
+        if line.trim().is_empty() || starts_block(line) {
+            break;
+        }
+        if lines.get(end + 1).and_then(|l| underline(l)).is_some() {
+            break;
+        }
+        end += 1;
+    }
+
+    let text: Vec<&str> = lines[..end].iter().map(|l| l.trim_end()).collect();
+    (Block::Paragraph(inlines(&text.join("\n"))), end)
+}
+
+pub(crate) fn inlines(s: &str) -> Vec<Inline> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut out = Vec::new();
+    let mut text = String::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        match inline_at(&chars, i) {
+            Some((inline, len)) => {
+                if !text.is_empty() {
+                    out.push(Inline::Text(std::mem::take(&mut text)));
+                }
+                out.push(inline);
+                i += len;
+            }
+            None => {
+                text.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    if !text.is_empty() {
+        out.push(Inline::Text(text));
+    }
+
+    out
+}
+
+fn starts_with(c: &[char], i: usize, pattern: &str) -> bool {
+    pattern
+        .chars()
+        .enumerate()
+        .all(|(k, p)| c.get(i + k) == Some(&p))
+}
+
+fn find(c: &[char], from: usize, pattern: &str) -> Option<usize> {
+    (from..c.len()).find(|&k| starts_with(c, k, pattern))
+}
+
+fn collect(c: &[char]) -> String {
+    c.iter().collect()
+}
+
+// Content between `delim` at `i` and the next `delim`, with the total length
+fn delimited(c: &[char], i: usize, delim: &str) -> Option<(String, usize)> {
+    if !starts_with(c, i, delim) {
+        return None;
+    }
+    let n = delim.chars().count();
+    let start = i + n;
+    match c.get(start) {
+        Some(c) if !c.is_whitespace() => (),
+        _ => return None,
+    }
+    let end = find(c, start, delim)?;
+    Some((collect(&c[start..end]), end + n - i))
+}
+
+fn inline_at(c: &[char], i: usize) -> Option<(Inline, usize)> {
+    let prev = if i > 0 { Some(c[i - 1]) } else { None };
+    let word_start = !matches!(prev, Some(p) if p.is_alphanumeric() || p == '_' || p == '/');
+
+    let styled = |delim, f: fn(Vec<Inline>) -> Inline| {
+        delimited(c, i, delim).map(|(inner, len)| (f(inlines(&inner)), len))
+    };
+
+    match c[i] {
+        '\n' => Some((Inline::LineBreak, 1)),
+        '*' => styled("**", Inline::Bold),
+        '/' if prev != Some(':') => styled("//", Inline::Italic),
+        '~' => styled("~~", Inline::Deleted),
+        '_' => styled("__", Inline::Underline),
+        '!' => styled("!!", Inline::Highlight),
+        '`' => delimited(c, i, "`").map(|(inner, len)| (Inline::Monospace(inner), len)),
+        '#' => delimited(c, i, "##")
+            .map(|(inner, len)| (Inline::Monospace(inner), len))
+            .or_else(|| hashtag(c, i, prev)),
+        '[' => link(c, i),
+        '{' => embed(c, i),
+        '@' if word_start => mention(c, i),
+        'h' if word_start => url(c, i),
+        m if word_start && MONOGRAMS.contains(m) => reference(c, i),
+        _ => None,
+    }
+}
+
+// Monogram and id at `i` with the length, if not followed by more word
+// characters
+fn object(c: &[char], i: usize) -> Option<(char, u32, usize)> {
+    let prefix = *c.get(i)?;
+    if !MONOGRAMS.contains(prefix) {
+        return None;
+    }
+    let digits = c[i + 1..].iter().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+    if matches!(c.get(i + 1 + digits), Some(n) if n.is_alphanumeric() || *n == '_') {
+        return None;
+    }
+    let id = collect(&c[i + 1..i + 1 + digits]).parse().ok()?;
+    Some((prefix, id, digits + 1))
+}
+
+fn reference(c: &[char], i: usize) -> Option<(Inline, usize)> {
+    let (prefix, id, mut len) = object(c, i)?;
+    let mut anchor = None;
+    if c.get(i + len) == Some(&'#') {
+        let n = c[i + len + 1..]
+            .iter()
+            .take_while(|c| c.is_alphanumeric() || **c == '-')
+            .count();
+        if n > 0 {
+            anchor = Some(collect(&c[i + len + 1..i + len + 1 + n]));
+            len += n + 1;
+        }
+    }
+    Some((Inline::Reference(ObjectRef { prefix, id, anchor }), len))
+}
+
+fn embed(c: &[char], i: usize) -> Option<(Inline, usize)> {
+    let (prefix, id, len) = object(c, i + 1)?;
+    let close = match c.get(i + 1 + len)? {
+        '}' => i + 1 + len,
+        // Embed options, e.g. `{F12, size=full}`
+        ',' => find(c, i + 1 + len, "}")?,
+        _ => return None,
+    };
+    let object = ObjectRef {
+        prefix,
+        id,
+        anchor: None,
+    };
+    Some((Inline::Embed(object), close + 1 - i))
+}
+
+fn link(c: &[char], i: usize) -> Option<(Inline, usize)> {
+    if starts_with(c, i, "[[") {
+        let end = find(c, i + 2, "]]")?;
+        let inner = collect(&c[i + 2..end]);
+        let (target, text) = match inner.split_once('|') {
+            Some((target, text)) => (target.trim(), Some(text.trim().to_string())),
+            None => (inner.trim(), None),
+        };
+        if target.is_empty() {
+            return None;
+        }
+        let link = Inline::Link {
+            target: target.to_string(),
+            text,
+        };
+        return Some((link, end + 2 - i));
+    }
+
+    // Markdown style [text](target)
+    let close = (i + 1..c.len()).find(|&k| c[k] == ']' || c[k] == '\n')?;
+    if c[close] != ']' || c.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = (close + 2..c.len()).find(|&k| c[k] == ')' || c[k].is_whitespace())?;
+    if c[end] != ')' || end == close + 2 {
+        return None;
+    }
+    let link = Inline::Link {
+        target: collect(&c[close + 2..end]),
+        text: Some(collect(&c[i + 1..close])),
+    };
+    Some((link, end + 1 - i))
+}
+
+fn url(c: &[char], i: usize) -> Option<(Inline, usize)> {
+    if !starts_with(c, i, "http://") && !starts_with(c, i, "https://") {
+        return None;
+    }
+    let mut end = (i..c.len())
+        .find(|&k| c[k].is_whitespace() || "<>\"|]".contains(c[k]))
+        .unwrap_or(c.len());
+    // Leave trailing punctuation and unbalanced parentheses outside
+    loop {
+        let last = c[end - 1];
+        let opened = c[i..end].iter().filter(|&&c| c == '(').count();
+        let closed = c[i..end].iter().filter(|&&c| c == ')').count();
+        if ".,;:!?'".contains(last) || (last == ')' && closed > opened) {
+            end -= 1;
+        } else {
+            break;
+        }
+    }
+    if c[i..end].ends_with(&['/', '/']) {
+        return None;
+    }
+    let link = Inline::Link {
+        target: collect(&c[i..end]),
+        text: None,
+    };
+    Some((link, end - i))
+}
+
+fn mention(c: &[char], i: usize) -> Option<(Inline, usize)> {
+    let mut n = c[i + 1..]
+        .iter()
+        .take_while(|c| c.is_alphanumeric() || "._-".contains(**c))
+        .count();
+    while n > 0 && ".-".contains(c[i + n]) {
+        n -= 1;
+    }
+    if n == 0 {
+        return None;
+    }
+    Some((Inline::Mention(collect(&c[i + 1..i + 1 + n])), n + 1))
+}
+
+fn hashtag(c: &[char], i: usize, prev: Option<char>) -> Option<(Inline, usize)> {
+    if matches!(prev, Some(p) if !p.is_whitespace() && p != '(') {
+        return None;
+    }
+    if !c.get(i + 1)?.is_alphabetic() {
+        return None;
+    }
+    let n = c[i + 1..]
+        .iter()
+        .take_while(|c| c.is_alphanumeric() || "_-".contains(**c))
+        .count();
+    Some((Inline::Hashtag(collect(&c[i + 1..i + 1 + n])), n + 1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn text(s: &str) -> Inline {
+        Inline::Text(s.to_string())
+    }
+
+    fn task(id: u32) -> ObjectRef {
+        ObjectRef {
+            prefix: 'T',
+            id,
+            anchor: None,
+        }
+    }
+
+    #[test]
+    fn headers() {
+        let doc = parse("= Title =\n\n== Sub\n\nUnderlined\n----------\n\n======");
+        assert_eq!(
+            vec![
+                Block::Header {
+                    level: 1,
+                    content: vec![text("Title")]
+                },
+                Block::Header {
+                    level: 2,
+                    content: vec![text("Sub")]
+                },
+                Block::Header {
+                    level: 2,
+                    content: vec![text("Underlined")]
+                },
+                Block::Paragraph(vec![text("======")]),
+            ],
+            doc.blocks
+        );
+    }
+
+    #[test]
+    fn paragraphs() {
+        let doc = parse("First line\nsecond line\n\nNext\n---\nAfter");
+        assert_eq!(
+            vec![
+                Block::Paragraph(vec![
+                    text("First line"),
+                    Inline::LineBreak,
+                    text("second line")
+                ]),
+                Block::Header {
+                    level: 2,
+                    content: vec![text("Next")]
+                },
+                Block::Paragraph(vec![text("After")]),
+            ],
+            doc.blocks
+        );
+        assert_eq!(
+            vec![Block::Paragraph(vec![text("a")]), Block::Rule],
+            parse("a\n\n---").blocks
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let doc = parse(
+            "- one\n- two\n  continued\n-- nested\n  - also nested\n- three\n\n# first\n1. second",
+        );
+        let item = |t: &str| ListItem {
+            content: inlines(t),
+            ..Default::default()
+        };
+
+        let mut two = item("two\ncontinued");
+        two.children.push(Block::List {
+            ordered: false,
+            items: vec![item("nested"), item("also nested")],
+        });
+        assert_eq!(
+            vec![
+                Block::List {
+                    ordered: false,
+                    items: vec![item("one"), two, item("three")],
+                },
+                Block::List {
+                    ordered: true,
+                    items: vec![item("first"), item("second")],
+                },
+            ],
+            doc.blocks
+        );
+    }
+
+    #[test]
+    fn lists_starting_nested() {
+        let doc = parse("-- a\n- b");
+        let item = |t: &str| ListItem {
+            content: inlines(t),
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![Block::List {
+                ordered: false,
+                items: vec![
+                    ListItem {
+                        children: vec![Block::List {
+                            ordered: false,
+                            items: vec![item("a")],
+                        }],
+                        ..Default::default()
+                    },
+                    item("b"),
+                ],
+            }],
+            doc.blocks
+        );
+    }
+
+    #[test]
+    fn checklists() {
+        let doc = parse("[ ] todo\n[x] done\n- [X] also done\n  - [] nested\n- not a check");
+        let items = doc.checklist();
+        let state: Vec<_> = items.iter().map(|i| (i.checked, i.text())).collect();
+        assert_eq!(
+            vec![
+                (Some(false), "todo".to_string()),
+                (Some(true), "done".to_string()),
+                (Some(true), "also done".to_string()),
+                (Some(false), "nested".to_string()),
+            ],
+            state
+        );
+    }
+
+    #[test]
+    fn code() {
+        let doc = parse(
+            "```lang=rust, name=main.rs\nfn main() {}\n```\n\n```\nlang=sh\nls```\n\n```py\nx = 1\n```\n\n  lang=c\n  int x;\n\n    int y;\n\n```inline```",
+        );
+        let code = |language: Option<&str>, code: &str| Block::Code {
+            language: language.map(String::from),
+            code: code.to_string(),
+        };
+        assert_eq!(
+            vec![
+                code(Some("rust"), "fn main() {}"),
+                code(Some("sh"), "ls"),
+                code(Some("py"), "x = 1"),
+                code(Some("c"), "int x;\n\n  int y;"),
+                code(None, "inline"),
+            ],
+            doc.blocks
+        );
+
+        // Markup isn't parsed in code
+        let doc = parse("```\n**T1** @user\n");
+        assert_eq!(vec![code(None, "**T1** @user")], doc.blocks);
+
+        // Multi-byte whitespace on a blank line in an indented block
+        let doc = parse("  a\n\u{3000}\n  b");
+        assert_eq!(vec![code(None, "a\n\u{3000}\nb")], doc.blocks);
+    }
+
+    #[test]
+    fn quotes() {
+        let doc = parse("> quoted **text**\n> - item\nafter");
+        assert_eq!(
+            vec![
+                Block::Quote(vec![
+                    Block::Paragraph(vec![text("quoted "), Inline::Bold(vec![text("text")])]),
+                    Block::List {
+                        ordered: false,
+                        items: vec![ListItem {
+                            content: vec![text("item")],
+                            ..Default::default()
+                        }],
+                    },
+                ]),
+                Block::Paragraph(vec![text("after")]),
+            ],
+            doc.blocks
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let doc = parse("| A | B |\n| --- | :-: |\n| [[ /x | y ]] | `a|b` |\n| T1 | |");
+        let table = match &doc.blocks[0] {
+            Block::Table(table) => table,
+            b => panic!("Not a table: {:?}", b),
+        };
+        assert_eq!(Some(vec![vec![text("A")], vec![text("B")]]), table.header);
+        assert_eq!(
+            vec![
+                vec![
+                    vec![Inline::Link {
+                        target: "/x".to_string(),
+                        text: Some("y".to_string())
+                    }],
+                    vec![Inline::Monospace("a|b".to_string())],
+                ],
+                vec![vec![Inline::Reference(task(1))], vec![]],
+            ],
+            table.rows
+        );
+
+        let doc = parse("| a | b |");
+        assert_eq!(
+            Block::Table(Table {
+                header: None,
+                rows: vec![vec![vec![text("a")], vec![text("b")]]]
+            }),
+            doc.blocks[0]
+        );
+    }
+
+    #[test]
+    fn styles() {
+        assert_eq!(
+            vec![
+                Inline::Bold(vec![text("bold "), Inline::Italic(vec![text("italic")])]),
+                text(" "),
+                Inline::Monospace("mono".to_string()),
+                text(" "),
+                Inline::Monospace("also mono".to_string()),
+                text(" "),
+                Inline::Deleted(vec![text("gone")]),
+                text(" "),
+                Inline::Underline(vec![text("under")]),
+                text(" "),
+                Inline::Highlight(vec![text("high")]),
+                text(" ** x"),
+            ],
+            inlines("**bold //italic//** `mono` ##also mono## ~~gone~~ __under__ !!high!! ** x")
+        );
+    }
+
+    #[test]
+    fn links() {
+        let link = |target: &str, text: Option<&str>| Inline::Link {
+            target: target.to_string(),
+            text: text.map(String::from),
+        };
+        assert_eq!(
+            vec![
+                link("https://example.com/a_(b)", None),
+                text(", "),
+                link("T1", Some("the task")),
+                text(" "),
+                link("/wiki/", None),
+                text(" "),
+                link("http://x.org", Some("md")),
+                text(" (see "),
+                link("http://y.org/", None),
+                text(")."),
+            ],
+            inlines(
+                "https://example.com/a_(b), [[T1 | the task]] [[/wiki/]] [md](http://x.org) (see http://y.org/)."
+            )
+        );
+    }
+
+    #[test]
+    fn references() {
+        let doc = parse("Fixes T123, see D45#2 and {F12, size=full}.\nNot: XT1 T1a /T2 T\n{T123}");
+        let refs: Vec<_> = doc.references().iter().map(|r| r.to_string()).collect();
+        assert_eq!(vec!["T123", "D45#2", "F12"], refs);
+
+        assert_eq!(
+            vec![
+                Inline::Embed(ObjectRef {
+                    prefix: 'F',
+                    id: 12,
+                    anchor: None
+                }),
+                text(" "),
+                Inline::Reference(task(3)),
+            ],
+            inlines("{F12} T3")
+        );
+    }
+
+    #[test]
+    fn mentions_and_hashtags() {
+        let doc = parse(
+            "Ping @alice and @bob.smith. mail@example.com\n#frontend (#ops) a#b #1 #frontend",
+        );
+        assert_eq!(vec!["alice", "bob.smith"], doc.mentions());
+        assert_eq!(vec!["frontend", "ops"], doc.hashtags());
+    }
+
+    #[test]
+    fn plain_text() {
+        let doc = parse("**T1** for @user in [[/x | here]]");
+        match &doc.blocks[0] {
+            Block::Paragraph(p) => assert_eq!("T1 for @user in here", crate::remarkup::text(p)),
+            b => panic!("Not a paragraph: {:?}", b),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{Client, Task};
+use phabricator_api::remarkup::process::{Context, Process};
+use phabricator_api::{ApiRequest, RequestError};
+
+impl Client {
+    /// Render Remarkup documents to HTML on the server, in one request
+    pub async fn render_remarkup(
+        &self,
+        context: Context,
+        contents: Vec<String>,
+    ) -> Result<Vec<String>, RequestError> {
+        if contents.is_empty() {
+            return Ok(Vec::new());
+        }
+        let n = contents.len();
+        let p = Process { context, contents };
+        let r = self.client().request(&p).await?;
+        if r.0.len() != n {
+            return Err(RequestError::Incomplete {
+                route: p.route().to_string(),
+            });
+        }
+        Ok(r.0.into_iter().map(|r| r.content).collect())
+    }
+
+    /// Render the descriptions of all `tasks` that don't have a rendered
+    /// description yet in a single request, see [`Task::description_html`]
+    pub async fn render_descriptions(&self, tasks: &[Task]) -> Result<(), RequestError> {
+        let pending: Vec<_> = tasks
+            .iter()
+            .filter(|t| t.rendered_description().is_none())
+            .map(|t| (t, t.description()))
+            .collect();
+        let contents = pending.iter().map(|(_, d)| d.clone()).collect();
+        let rendered = self.render_remarkup(Context::Maniphest, contents).await?;
+
+        for ((task, description), html) in pending.iter().zip(rendered) {
+            task.set_description_html(description, html);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use futures::prelude::*;
+    use phabricator_mock::PhabMockServer;
+
+    #[tokio::test]
+    async fn descriptions() {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        for id in [100, 200, 300] {
+            let t = phabricator_mock::task()
+                .id(id)
+                .full_name(format!("Task {}", id))
+                .description(format!("Task **{}**", id))
+                .author(user.clone())
+                .owner(user.clone())
+                .status(m.default_status())
+                .priority(m.default_priority())
+                .build()
+                .unwrap();
+            m.add_task(t);
+        }
+
+        let client = Client::new(m.uri(), m.token().to_string());
+        let tasks: Vec<Task> = client
+            .tasks(&[100, 200, 300])
+            .query()
+            .try_collect()
+            .await
+            .unwrap();
+
+        let requests = m.n_requests().await;
+        client.render_descriptions(&tasks).await.unwrap();
+        assert_eq!(requests + 1, m.n_requests().await);
+
+        for t in &tasks {
+            assert_eq!(
+                format!("<p>Task <strong>{}</strong></p>", t.id()),
+                t.description_html().await.unwrap()
+            );
+        }
+        // Everything was already rendered
+        client.render_descriptions(&tasks).await.unwrap();
+        assert_eq!(requests + 1, m.n_requests().await);
+    }
+
+    #[tokio::test]
+    async fn single() {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        m.new_simple_task(100, &user);
+
+        let client = Client::new(m.uri(), m.token().to_string());
+        let task: Task = client
+            .tasks(&[100])
+            .query()
+            .try_next()
+            .await
+            .unwrap()
+            .unwrap();
+        let html = task.description_html().await.unwrap();
+        assert!(html.starts_with("<p>"), "Unexpected html: {}", html);
+
+        let requests = m.n_requests().await;
+        assert_eq!(html, task.description_html().await.unwrap());
+        assert_eq!(requests, m.n_requests().await);
+    }
+}
@@ -0,0 +1,442 @@
+use super::{Block, Document, Inline, ListItem, ObjectRef, Table};
+use crate::Client;
+use std::fmt::Write;
+use url::Url;
+
+/// Renders Remarkup documents as CommonMark or HTML, linking object
+/// references, mentions and hashtags to the instance at `base`
+#[derive(Clone, Debug)]
+pub struct Renderer {
+    base: Url,
+}
+
+impl Client {
+    pub fn remarkup_renderer(&self) -> Renderer {
+        Renderer::new(self.client().base().clone())
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn escape_markdown(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\`*_[]<>#~|!".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// Shortest run of backticks not contained in `s`
+fn backticks(s: &str, min: usize) -> String {
+    let mut n = min;
+    while s.contains(&"`".repeat(n)) {
+        n += 1;
+    }
+    "`".repeat(n)
+}
+
+fn prefix_lines(s: &str, first: &str, rest: &str) -> String {
+    let mut out = String::new();
+    for (i, line) in s.lines().enumerate() {
+        let prefix = if i == 0 { first } else { rest };
+        if line.is_empty() {
+            out.push_str(prefix.trim_end());
+        } else {
+            out.push_str(prefix);
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+impl Renderer {
+    pub fn new(base: Url) -> Self {
+        Renderer { base }
+    }
+
+    fn instance(&self, path: &str) -> Option<Url> {
+        self.base.join(path).ok()
+    }
+
+    fn object_url(&self, object: &ObjectRef) -> Option<Url> {
+        let mut url = self.instance(&object.monogram())?;
+        url.set_fragment(object.anchor.as_deref());
+        Some(url)
+    }
+
+    // Resolve a link target; relative paths are on the instance and plain
+    // names are wiki pages. None for anything but web and mail links.
+    fn link_url(&self, target: &str) -> Option<Url> {
+        let url = match Url::parse(target) {
+            Ok(url) => url,
+            Err(_) => match target.strip_prefix('/') {
+                Some(path) => self.instance(path)?,
+                None => {
+                    let slug = target.trim().to_lowercase().replace(' ', "_");
+                    self.instance(&format!("w/{}/", slug.trim_matches('/')))?
+                }
+            },
+        };
+        match url.scheme() {
+            "http" | "https" | "mailto" => Some(url),
+            _ => None,
+        }
+    }
+
+    // Target and text of all linking inlines
+    fn inline_link(&self, inline: &Inline) -> Option<(Option<Url>, String)> {
+        match inline {
+            Inline::Link { target, text } => Some((
+                self.link_url(target),
+                text.clone().unwrap_or_else(|| target.clone()),
+            )),
+            Inline::Reference(r) => Some((self.object_url(r), r.to_string())),
+            Inline::Embed(r) => Some((self.object_url(r), r.monogram())),
+            Inline::Mention(m) => Some((self.instance(&format!("p/{}/", m)), format!("@{}", m))),
+            Inline::Hashtag(h) => Some((
+                self.instance(&format!("tag/{}/", h.to_lowercase())),
+                format!("#{}", h),
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn markdown(&self, document: &Document) -> String {
+        self.markdown_blocks(&document.blocks)
+    }
+
+    fn markdown_blocks(&self, blocks: &[Block]) -> String {
+        let blocks: Vec<_> = blocks.iter().map(|b| self.markdown_block(b)).collect();
+        blocks.join("\n")
+    }
+
+    fn markdown_block(&self, block: &Block) -> String {
+        match block {
+            Block::Header { level, content } => format!(
+                "{} {}\n",
+                "#".repeat(*level as usize),
+                self.markdown_inlines(content)
+            ),
+            Block::Paragraph(content) => format!("{}\n", self.markdown_inlines(content)),
+            Block::List { ordered, items } => self.markdown_list(*ordered, items),
+            Block::Code { language, code } => {
+                let fence = backticks(code, 3);
+                format!(
+                    "{}{}\n{}\n{}\n",
+                    fence,
+                    language.as_deref().unwrap_or_default(),
+                    code,
+                    fence
+                )
+            }
+            Block::Quote(blocks) => prefix_lines(&self.markdown_blocks(blocks), "> ", "> "),
+            Block::Table(table) => self.markdown_table(table),
+            Block::Rule => "---\n".to_string(),
+        }
+    }
+
+    fn markdown_list(&self, ordered: bool, items: &[ListItem]) -> String {
+        let mut out = String::new();
+        for (n, item) in items.iter().enumerate() {
+            let mut marker = if ordered {
+                format!("{}. ", n + 1)
+            } else {
+                "- ".to_string()
+            };
+            match item.checked {
+                Some(true) => marker.push_str("[x] "),
+                Some(false) => marker.push_str("[ ] "),
+                None => (),
+            }
+            let indent = " ".repeat(if ordered { marker.len().min(4) } else { 2 });
+
+            let mut content = self.markdown_inlines(&item.content);
+            content.push('\n');
+            for child in &item.children {
+                content.push_str(&self.markdown_block(child));
+            }
+            out.push_str(&prefix_lines(&content, &marker, &indent));
+        }
+        out
+    }
+
+    fn markdown_table(&self, table: &Table) -> String {
+        let columns = table
+            .header
+            .iter()
+            .chain(table.rows.iter())
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0);
+        let row = |cells: &[Vec<Inline>]| {
+            let mut cells: Vec<_> = cells
+                .iter()
+                .map(|c| self.markdown_inlines(c).replace("\\\n", " "))
+                .collect();
+            cells.resize(columns, String::new());
+            format!("| {} |\n", cells.join(" | "))
+        };
+
+        // CommonMark tables always have a header
+        let mut out = match &table.header {
+            Some(header) => row(header),
+            None => row(&[]),
+        };
+        out.push_str(&format!("|{}\n", " --- |".repeat(columns)));
+        for r in &table.rows {
+            out.push_str(&row(r));
+        }
+        out
+    }
+
+    fn markdown_inlines(&self, inlines: &[Inline]) -> String {
+        let mut out = String::new();
+        for inline in inlines {
+            if let Some((url, text)) = self.inline_link(inline) {
+                match url {
+                    Some(url) if matches!(inline, Inline::Link { text: None, .. }) => {
+                        write!(out, "<{}>", url).unwrap()
+                    }
+                    Some(url) => write!(out, "[{}]({})", escape_markdown(&text), url).unwrap(),
+                    None => out.push_str(&escape_markdown(&text)),
+                }
+                continue;
+            }
+
+            match inline {
+                Inline::Text(t) => out.push_str(&escape_markdown(t)),
+                Inline::Bold(c) => write!(out, "**{}**", self.markdown_inlines(c)).unwrap(),
+                Inline::Italic(c) => write!(out, "*{}*", self.markdown_inlines(c)).unwrap(),
+                Inline::Deleted(c) => write!(out, "~~{}~~", self.markdown_inlines(c)).unwrap(),
+                // No CommonMark equivalent, so fall back to inline HTML
+                Inline::Underline(c) => write!(out, "<u>{}</u>", self.markdown_inlines(c)).unwrap(),
+                Inline::Highlight(c) => {
+                    write!(out, "<mark>{}</mark>", self.markdown_inlines(c)).unwrap()
+                }
+                Inline::Monospace(m) => {
+                    let ticks = backticks(m, 1);
+                    // Pad to keep backticks at the edges apart from the fence
+                    let pad = if m.starts_with('`') || m.ends_with('`') {
+                        " "
+                    } else {
+                        ""
+                    };
+                    write!(out, "{}{}{}{}{}", ticks, pad, m, pad, ticks).unwrap()
+                }
+                Inline::LineBreak => out.push_str("\\\n"),
+                _ => unreachable!("Links are handled above"),
+            }
+        }
+        out
+    }
+
+    /// HTML with all text escaped and only links to web and mail urls
+    pub fn html(&self, document: &Document) -> String {
+        self.html_blocks(&document.blocks)
+    }
+
+    fn html_blocks(&self, blocks: &[Block]) -> String {
+        blocks.iter().map(|b| self.html_block(b)).collect()
+    }
+
+    fn html_block(&self, block: &Block) -> String {
+        match block {
+            Block::Header { level, content } => {
+                format!("<h{}>{}</h{}>\n", level, self.html_inlines(content), level)
+            }
+            Block::Paragraph(content) => format!("<p>{}</p>\n", self.html_inlines(content)),
+            Block::List { ordered, items } => {
+                let tag = if *ordered { "ol" } else { "ul" };
+                let mut out = format!("<{}>\n", tag);
+                for item in items {
+                    out.push_str("<li>");
+                    match item.checked {
+                        Some(true) => out.push_str("<input type=\"checkbox\" checked disabled /> "),
+                        Some(false) => out.push_str("<input type=\"checkbox\" disabled /> "),
+                        None => (),
+                    }
+                    out.push_str(&self.html_inlines(&item.content));
+                    if !item.children.is_empty() {
+                        out.push('\n');
+                        out.push_str(&self.html_blocks(&item.children));
+                    }
+                    out.push_str("</li>\n");
+                }
+                writeln!(out, "</{}>", tag).unwrap();
+                out
+            }
+            Block::Code { language, code } => match language {
+                Some(language) => format!(
+                    "<pre><code class=\"language-{}\">{}</code></pre>\n",
+                    escape_html(language),
+                    escape_html(code)
+                ),
+                None => format!("<pre><code>{}</code></pre>\n", escape_html(code)),
+            },
+            Block::Quote(blocks) => {
+                format!("<blockquote>\n{}</blockquote>\n", self.html_blocks(blocks))
+            }
+            Block::Table(table) => {
+                let row = |cells: &[Vec<Inline>], tag: &str| {
+                    let cells: String = cells
+                        .iter()
+                        .map(|c| format!("<{}>{}</{}>", tag, self.html_inlines(c), tag))
+                        .collect();
+                    format!("<tr>{}</tr>\n", cells)
+                };
+                let mut out = String::from("<table>\n");
+                if let Some(header) = &table.header {
+                    write!(out, "<thead>\n{}</thead>\n", row(header, "th")).unwrap();
+                }
+                out.push_str("<tbody>\n");
+                for r in &table.rows {
+                    out.push_str(&row(r, "td"));
+                }
+                out.push_str("</tbody>\n</table>\n");
+                out
+            }
+            Block::Rule => "<hr />\n".to_string(),
+        }
+    }
+
+    fn html_inlines(&self, inlines: &[Inline]) -> String {
+        let mut out = String::new();
+        for inline in inlines {
+            if let Some((url, text)) = self.inline_link(inline) {
+                match url {
+                    Some(url) => write!(
+                        out,
+                        "<a href=\"{}\">{}</a>",
+                        escape_html(url.as_str()),
+                        escape_html(&text)
+                    )
+                    .unwrap(),
+                    None => out.push_str(&escape_html(&text)),
+                }
+                continue;
+            }
+
+            let tagged =
+                |tag: &str, c: &[Inline]| format!("<{}>{}</{}>", tag, self.html_inlines(c), tag);
+            match inline {
+                Inline::Text(t) => out.push_str(&escape_html(t)),
+                Inline::Bold(c) => out.push_str(&tagged("strong", c)),
+                Inline::Italic(c) => out.push_str(&tagged("em", c)),
+                Inline::Deleted(c) => out.push_str(&tagged("del", c)),
+                Inline::Underline(c) => out.push_str(&tagged("u", c)),
+                Inline::Highlight(c) => out.push_str(&tagged("mark", c)),
+                Inline::Monospace(m) => write!(out, "<code>{}</code>", escape_html(m)).unwrap(),
+                Inline::LineBreak => out.push_str("<br />\n"),
+                _ => unreachable!("Links are handled above"),
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::remarkup::parse;
+
+    fn renderer() -> Renderer {
+        Renderer::new(Url::parse("https://phab.example.com/").unwrap())
+    }
+
+    #[test]
+    fn markdown() {
+        let doc = parse(
+            "= Plan =\n\nFixes T12#3 for @alice, see [[ /w/docs | the docs ]] and #ops.\n\
+             Next **line** with `code` and a_b\n\n\
+             - [x] done\n- [ ] todo\n-- nested\n\n\
+             ```lang=rust\nfn main() {}\n```\n\n\
+             > quoted\n>\n> more\n\n\
+             | A | B |\n| - | - |\n| 1 | x |",
+        );
+
+        let expected = "# Plan\n\
+\n\
+Fixes [T12\\#3](https://phab.example.com/T12#3) for [@alice](https://phab.example.com/p/alice/), see [the docs](https://phab.example.com/w/docs) and [\\#ops](https://phab.example.com/tag/ops/).\\\n\
+Next **line** with `code` and a\\_b\n\
+\n\
+- [x] done\n\
+- [ ] todo\n  - nested\n\
+\n\
+```rust\n\
+fn main() {}\n\
+```\n\
+\n\
+> quoted\n\
+>\n\
+> more\n\
+\n\
+| A | B |\n\
+| --- | --- |\n\
+| 1 | x |\n";
+        assert_eq!(expected, renderer().markdown(&doc));
+    }
+
+    #[test]
+    fn markdown_edge_cases() {
+        let r = renderer();
+        assert_eq!("``a`b`` `` `x` ``\n", r.markdown(&parse("##a`b## ##`x`##")));
+        assert_eq!(
+            "````\n```\n````\n",
+            r.markdown(&Document {
+                blocks: vec![Block::Code {
+                    language: None,
+                    code: "```".to_string()
+                }]
+            })
+        );
+        assert_eq!(
+            "|  |  |\n| --- | --- |\n| a | b |\n| c |  |\n",
+            r.markdown(&parse("| a | b |\n| c |"))
+        );
+        assert_eq!(
+            "1. one\n2. two\n   1. sub\n",
+            r.markdown(&parse("# one\n# two\n## sub"))
+        );
+    }
+
+    #[test]
+    fn html() {
+        let doc = parse(
+            "== <Title> ==\n\nSee {F7} by @bob:\n//\"quoted\"// & [[ javascript:alert(1) | click ]]\n\n\
+             [x] checked\n\n```\n<script>\n```\n\n| h |\n| - |\n| T1 |",
+        );
+        let expected = "<h2>&lt;Title&gt;</h2>\n\
+<p>See <a href=\"https://phab.example.com/F7\">F7</a> by <a href=\"https://phab.example.com/p/bob/\">@bob</a>:<br />\n\
+<em>&quot;quoted&quot;</em> &amp; click</p>\n\
+<ul>\n\
+<li><input type=\"checkbox\" checked disabled /> checked</li>\n\
+</ul>\n\
+<pre><code>&lt;script&gt;</code></pre>\n\
+<table>\n\
+<thead>\n\
+<tr><th>h</th></tr>\n\
+</thead>\n\
+<tbody>\n\
+<tr><td><a href=\"https://phab.example.com/T1\">T1</a></td></tr>\n\
+</tbody>\n\
+</table>\n";
+        assert_eq!(expected, renderer().html(&doc));
+    }
+}
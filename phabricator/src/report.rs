@@ -0,0 +1,371 @@
+use crate::export::csv_field;
+use crate::tree::{Direction, TaskTree};
+use crate::Task;
+use chrono::{NaiveDate, TimeZone, Utc};
+use phabricator_api::types::Phid;
+use rust_decimal::Decimal;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Totals {
+    pub tasks: usize,
+    pub points: Decimal,
+}
+
+impl Totals {
+    fn add(&mut self, task: &Task) {
+        self.tasks += 1;
+        self.points += task.points().unwrap_or_default();
+    }
+}
+
+/// Points totals over a set of tasks. Tasks are counted in every project and
+/// column they're in; projects and columns are only known for tasks queried
+/// with them.
+#[derive(Clone, Debug, Default)]
+pub struct PointsReport {
+    pub total: Totals,
+    pub by_project: HashMap<Phid, Totals>,
+    pub by_owner: HashMap<Option<Phid>, Totals>,
+    pub by_status: HashMap<String, Totals>,
+    pub by_column: HashMap<Phid, Totals>,
+}
+
+impl PointsReport {
+    pub fn new<'a, I>(tasks: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Task>,
+    {
+        let mut report = PointsReport::default();
+
+        for t in tasks {
+            report.total.add(t);
+            report.by_owner.entry(t.owner()).or_default().add(t);
+            report.by_status.entry(t.status()).or_default().add(t);
+            for p in t.resolved_projects().unwrap_or_default() {
+                report
+                    .by_project
+                    .entry(p.phid().clone())
+                    .or_default()
+                    .add(t);
+            }
+            for c in t.columns().unwrap_or_default() {
+                report.by_column.entry(c.phid).or_default().add(t);
+            }
+        }
+
+        report
+    }
+
+    /// One `group,key,tasks,points` line per total, sorted by group and key
+    pub fn to_csv(&self) -> String {
+        fn rows<'a, K: 'a, I, F>(
+            group: &str,
+            totals: I,
+            key: F,
+        ) -> Vec<(String, String, &'a Totals)>
+        where
+            I: IntoIterator<Item = (&'a K, &'a Totals)>,
+            F: Fn(&K) -> String,
+        {
+            let mut rows: Vec<_> = totals
+                .into_iter()
+                .map(|(k, t)| (group.to_string(), key(k), t))
+                .collect();
+            rows.sort_by(|a, b| a.1.cmp(&b.1));
+            rows
+        }
+
+        let mut out = String::from("group,key,tasks,points\n");
+        let all = std::iter::once(("total".to_string(), String::new(), &self.total))
+            .chain(rows("project", &self.by_project, |p| p.0.clone()))
+            .chain(rows("owner", &self.by_owner, |o| {
+                o.as_ref().map(|o| o.0.clone()).unwrap_or_default()
+            }))
+            .chain(rows("status", &self.by_status, |s| s.clone()))
+            .chain(rows("column", &self.by_column, |c| c.0.clone()));
+
+        for (group, key, totals) in all {
+            writeln!(
+                out,
+                "{},{},{},{}",
+                group,
+                csv_field(&key),
+                totals.tasks,
+                totals.points
+            )
+            .unwrap();
+        }
+        out
+    }
+}
+
+/// Points of each task in a subtask tree including the points of all its
+/// subtasks, counting tasks reachable over multiple paths only once.
+///
+/// Returns `None` for a tree of parents, as points don't roll up over those.
+pub fn rollup(tree: &TaskTree) -> Option<HashMap<u32, Decimal>> {
+    fn below(tree: &TaskTree, task: &Task, seen: &mut HashSet<u32>) {
+        for c in tree.children(task) {
+            if seen.insert(c.id()) {
+                below(tree, &c, seen);
+            }
+        }
+    }
+
+    if tree.direction() != Direction::Subtasks {
+        return None;
+    }
+
+    let points = tree
+        .tasks()
+        .map(|t| {
+            let mut seen = HashSet::new();
+            seen.insert(t.id());
+            below(tree, t, &mut seen);
+
+            let points = tree
+                .tasks()
+                .filter(|t| seen.contains(&t.id()))
+                .map(|t| t.points().unwrap_or_default())
+                .sum();
+            (t.id(), points)
+        })
+        .collect();
+    Some(points)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BurnPoint {
+    pub date: NaiveDate,
+    /// Points of all tasks created by the end of the day
+    pub scope: Decimal,
+    /// Points of the tasks closed by the end of the day
+    pub completed: Decimal,
+}
+
+impl BurnPoint {
+    pub fn remaining(&self) -> Decimal {
+        self.scope - self.completed
+    }
+}
+
+/// Daily burndown/burnup series from `start` up to and including `end`.
+///
+/// This is based on the creation and close dates of the tasks, so tasks
+/// that were reopened count as open until closed again.
+pub fn burndown<'a, I>(tasks: I, start: NaiveDate, end: NaiveDate) -> Vec<BurnPoint>
+where
+    I: IntoIterator<Item = &'a Task>,
+{
+    let tasks: Vec<_> = tasks
+        .into_iter()
+        .map(|t| (t.created(), t.closed(), t.points().unwrap_or_default()))
+        .collect();
+
+    let mut series = Vec::new();
+    let mut date = start;
+    while date <= end {
+        let next = date.succ_opt().expect("Date out of range");
+        let day_end = Utc.from_utc_datetime(&next.and_hms_opt(0, 0, 0).unwrap());
+
+        let mut point = BurnPoint {
+            date,
+            scope: Decimal::ZERO,
+            completed: Decimal::ZERO,
+        };
+        for (created, closed, points) in &tasks {
+            if *created < day_end {
+                point.scope += points;
+            }
+            if closed.iter().any(|c| *c < day_end) {
+                point.completed += points;
+            }
+        }
+        series.push(point);
+        date = next;
+    }
+
+    series
+}
+
+/// `date,scope,completed,remaining` lines for a burndown series
+pub fn burndown_csv(series: &[BurnPoint]) -> String {
+    let mut out = String::from("date,scope,completed,remaining\n");
+    for p in series {
+        writeln!(
+            out,
+            "{},{},{},{}",
+            p.date,
+            p.scope,
+            p.completed,
+            p.remaining()
+        )
+        .unwrap();
+    }
+    out
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use crate::Client;
+    use futures::prelude::*;
+    use phabricator_mock::task;
+    use phabricator_mock::PhabMockServer;
+
+    const DAY: u64 = 24 * 60 * 60;
+    // 2021-03-01 00:00:00 UTC
+    const START: u64 = 1614556800;
+
+    async fn setup() -> (PhabMockServer, Client) {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        let other = m.new_user("other", "Other User");
+        let project = phabricator_mock::project()
+            .id(10)
+            .name("Project")
+            .build()
+            .unwrap();
+        let column = phabricator_mock::column()
+            .id(15)
+            .name("Backlog")
+            .project(project.clone())
+            .build()
+            .unwrap();
+        project.add_column(column.clone());
+        m.add_project(project.clone());
+        let resolved = m.new_status("resolved", "Resolved", None);
+
+        let specs = [
+            (100, 5, &user, None),
+            (200, 3, &user, Some(START + DAY)),
+            (300, 2, &other, Some(START + 2 * DAY + 10)),
+        ];
+        for (id, points, owner, closed) in specs.iter() {
+            let mut t = phabricator_mock::task()
+                .id(*id)
+                .full_name(format!("Task {}", id))
+                .description("")
+                .points(Decimal::new(*points, 0))
+                .author(user.clone())
+                .owner((*owner).clone())
+                .priority(m.default_priority())
+                .date_created(START)
+                .projects(vec![project.clone()])
+                .columns(vec![column.clone()]);
+            t = match closed {
+                Some(closed) => t.status(resolved.clone()).date_closed(*closed),
+                None => t.status(m.default_status()),
+            };
+            m.add_task(t.build().unwrap());
+        }
+        let t100 = m.get_task(100).unwrap();
+        task::link(&t100, &m.get_task(200).unwrap());
+        task::link(&m.get_task(200).unwrap(), &m.get_task(300).unwrap());
+
+        let client = Client::new(m.uri(), m.token().to_string());
+        (m, client)
+    }
+
+    async fn tasks(client: &Client) -> Vec<Task> {
+        client
+            .tasks(&[100, 200, 300])
+            .projects()
+            .columns()
+            .query()
+            .try_collect()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn totals() {
+        let (m, client) = setup().await;
+        let tasks = tasks(&client).await;
+        let report = PointsReport::new(&tasks);
+
+        let totals = |tasks, points| Totals {
+            tasks,
+            points: Decimal::new(points, 0),
+        };
+        assert_eq!(totals(3, 10), report.total);
+        assert_eq!(
+            totals(3, 10),
+            report.by_project[tasks[0].projects().await.unwrap()[0].phid()]
+        );
+        assert_eq!(totals(1, 5), report.by_status["open"]);
+        assert_eq!(totals(2, 5), report.by_status["resolved"]);
+        assert_eq!(1, report.by_column.len());
+
+        let user = m.get_task(100).unwrap().owner.clone().unwrap();
+        let user = Phid(user.phid.to_string());
+        assert_eq!(totals(2, 8), report.by_owner[&Some(user.clone())]);
+
+        let csv = report.to_csv();
+        assert!(csv.starts_with("group,key,tasks,points\ntotal,,3,10\n"));
+        assert!(csv.contains(&format!("owner,{},2,8\n", user.0)));
+        assert!(csv.contains("status,resolved,2,5\n"));
+    }
+
+    #[tokio::test]
+    async fn rolled_up() {
+        let (_m, client) = setup().await;
+        let tasks = tasks(&client).await;
+        let root = client.cached_task(100).unwrap();
+        assert_eq!(3, tasks.len());
+
+        let tree = client
+            .task_tree(std::iter::once(&root))
+            .query()
+            .await
+            .unwrap();
+        let points = rollup(&tree).unwrap();
+        assert_eq!(Decimal::new(10, 0), points[&100]);
+        assert_eq!(Decimal::new(5, 0), points[&200]);
+        assert_eq!(Decimal::new(2, 0), points[&300]);
+
+        let leaf = client.cached_task(300).unwrap();
+        let tree = client
+            .task_tree(std::iter::once(&leaf))
+            .parents()
+            .query()
+            .await
+            .unwrap();
+        assert!(rollup(&tree).is_none());
+    }
+
+    #[tokio::test]
+    async fn burn() {
+        let (_m, client) = setup().await;
+        let tasks = tasks(&client).await;
+
+        let start = NaiveDate::from_ymd_opt(2021, 3, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2021, 3, 4).unwrap();
+        let series = burndown(&tasks, start, end);
+
+        let completed: Vec<_> = series.iter().map(|p| p.completed).collect();
+        assert_eq!(
+            vec![
+                Decimal::ZERO,
+                Decimal::new(3, 0),
+                Decimal::new(5, 0),
+                Decimal::new(5, 0)
+            ],
+            completed
+        );
+        assert!(series.iter().all(|p| p.scope == Decimal::new(10, 0)));
+        assert_eq!(Decimal::new(7, 0), series[1].remaining());
+
+        let csv = burndown_csv(&series);
+        assert_eq!(
+            "date,scope,completed,remaining\n\
+             2021-03-01,10,0,10\n\
+             2021-03-02,10,3,7\n\
+             2021-03-03,10,5,5\n\
+             2021-03-04,10,5,5\n",
+            csv
+        );
+    }
+}
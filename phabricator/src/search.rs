@@ -13,7 +13,7 @@ pub type QueryData<T> = (Vec<T>, Option<Cursor>);
 #[derive(Debug, Error)]
 pub enum QueryError {
     #[error("Request Failed: {0}")]
-    RequestError(#[from] reqwest::Error),
+    RequestError(#[from] RequestError),
 }
 
 enum QueryState<'f, T> {
@@ -0,0 +1,234 @@
+use crate::tree::Direction;
+use crate::{Client, Column, Project, Task};
+use chrono::{DateTime, Utc};
+use phabricator_api::types::Phid;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+/// Plain data copy of a [`Task`]. Fields that weren't retrieved for the task
+/// (projects, columns and relations) are `None`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskSnapshot {
+    pub id: u32,
+    pub phid: Phid,
+    pub title: String,
+    pub description: String,
+    pub status: String,
+    pub owner: Option<Phid>,
+    pub points: Option<Decimal>,
+    pub created: DateTime<Utc>,
+    pub modified: DateTime<Utc>,
+    pub closed: Option<DateTime<Utc>>,
+    pub projects: Option<Vec<Phid>>,
+    pub columns: Option<Vec<Column>>,
+    pub parents: Option<Vec<Phid>>,
+    pub subtasks: Option<Vec<Phid>>,
+}
+
+impl From<&Task> for TaskSnapshot {
+    fn from(task: &Task) -> Self {
+        task.snapshot()
+    }
+}
+
+/// Plain data copy of a [`Project`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectSnapshot {
+    pub id: u32,
+    pub phid: Phid,
+    pub title: String,
+    pub slug: Option<String>,
+    pub description: Option<String>,
+}
+
+impl From<&Project> for ProjectSnapshot {
+    fn from(project: &Project) -> Self {
+        project.snapshot()
+    }
+}
+
+impl Client {
+    /// Load project snapshots into the cache, updating already cached
+    /// projects in place
+    pub fn hydrate_projects<I>(&self, snapshots: I) -> Vec<Project>
+    where
+        I: IntoIterator<Item = ProjectSnapshot>,
+    {
+        self.update_cache(|cache| {
+            snapshots
+                .into_iter()
+                .map(|s| match cache.projects.entry(s.phid.clone()) {
+                    Entry::Vacant(v) => v.insert(Project::from_snapshot(s, self)).clone(),
+                    Entry::Occupied(mut o) => {
+                        let p = o.get_mut();
+                        p.update_snapshot(s);
+                        p.clone()
+                    }
+                })
+                .collect()
+        })
+    }
+
+    /// Load task snapshots into the cache, updating already cached tasks in
+    /// place. Projects should be hydrated first to be resolved; relations
+    /// are only restored if all related tasks are part of `snapshots`.
+    pub fn hydrate_tasks<I>(&self, snapshots: I) -> Vec<Task>
+    where
+        I: IntoIterator<Item = TaskSnapshot>,
+    {
+        let mut relations = Vec::new();
+        let tasks: Vec<Task> = self.update_cache(|cache| {
+            let projects = cache.projects.clone();
+            snapshots
+                .into_iter()
+                .map(|mut s| {
+                    let parents = s.parents.take();
+                    let subtasks = s.subtasks.take();
+                    let task = match cache.task_mut(s.id) {
+                        Some(t) => {
+                            t.update_snapshot(s, &projects);
+                            t.clone()
+                        }
+                        None => {
+                            let t = Task::from_snapshot(s, self, &projects);
+                            cache.insert_task(t.clone());
+                            t
+                        }
+                    };
+                    relations.push((task.clone(), parents, subtasks));
+                    task
+                })
+                .collect()
+        });
+
+        let by_phid: HashMap<&Phid, &Task> = tasks.iter().map(|t| (t.phid(), t)).collect();
+        let resolve = |phids: Vec<Phid>| -> Option<Vec<Task>> {
+            phids
+                .iter()
+                .map(|phid| by_phid.get(phid).map(|&t| t.clone()))
+                .collect()
+        };
+        for (task, parents, subtasks) in relations {
+            if let Some(parents) = parents.and_then(resolve) {
+                task.set_related(Direction::Parents, parents);
+            }
+            if let Some(subtasks) = subtasks.and_then(resolve) {
+                task.set_related(Direction::Subtasks, subtasks);
+            }
+        }
+
+        tasks
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use futures::prelude::*;
+    use phabricator_mock::task;
+    use phabricator_mock::PhabMockServer;
+
+    async fn setup() -> PhabMockServer {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        let project = phabricator_mock::project()
+            .id(10)
+            .name("Project")
+            .build()
+            .unwrap();
+        m.add_project(project.clone());
+        let column = phabricator_mock::column()
+            .id(15)
+            .name("Backlog")
+            .project(project.clone())
+            .build()
+            .unwrap();
+        project.add_column(column.clone());
+
+        let t = phabricator_mock::task()
+            .id(100)
+            .full_name("Test task 100")
+            .description("100 test description")
+            .author(user.clone())
+            .owner(user.clone())
+            .status(m.default_status())
+            .priority(m.default_priority())
+            .projects(vec![project])
+            .columns(vec![column])
+            .date_created(500)
+            .date_modified(1000)
+            .build()
+            .unwrap();
+        m.add_task(t.clone());
+        let sub = m.new_simple_task(200, &user);
+        task::link(&t, &sub);
+
+        m
+    }
+
+    async fn snapshots(m: &PhabMockServer) -> (Vec<ProjectSnapshot>, Vec<TaskSnapshot>) {
+        let client = Client::new(m.uri(), m.token().to_string());
+        let tasks: Vec<Task> = client
+            .tasks(&[100])
+            .projects()
+            .columns()
+            .query()
+            .try_collect()
+            .await
+            .unwrap();
+        let subtasks = tasks[0].subtasks().await.unwrap();
+        let projects = tasks[0].projects().await.unwrap();
+
+        (
+            projects.iter().map(ProjectSnapshot::from).collect(),
+            tasks.iter().chain(&subtasks).map(Task::snapshot).collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn roundtrip() {
+        let m = setup().await;
+        let (projects, tasks) = snapshots(&m).await;
+
+        let json = serde_json::to_string(&(&projects, &tasks)).unwrap();
+        let (projects, tasks): (Vec<ProjectSnapshot>, Vec<TaskSnapshot>) =
+            serde_json::from_str(&json).unwrap();
+
+        let t = &tasks[0];
+        assert_eq!(100, t.id);
+        assert_eq!("Test task 100", t.title);
+        assert_eq!(500, t.created.timestamp());
+        assert_eq!(Some(vec![projects[0].phid.clone()]), t.projects);
+        assert_eq!("Backlog", t.columns.as_ref().unwrap()[0].name);
+        assert_eq!(Some(vec![tasks[1].phid.clone()]), t.subtasks);
+        // Relations of the subtask itself were never retrieved
+        assert_eq!(None, tasks[1].subtasks);
+    }
+
+    #[tokio::test]
+    async fn hydrate() {
+        let m = setup().await;
+        let (projects, tasks) = snapshots(&m).await;
+        let requests = m.n_requests().await;
+
+        let client = Client::new(m.uri(), m.token().to_string());
+        client.hydrate_projects(projects);
+        let hydrated = client.hydrate_tasks(tasks.clone());
+        assert_eq!(2, hydrated.len());
+
+        let task = client.cached_task(100).expect("Task not hydrated");
+        assert_eq!(tasks[0], task.snapshot());
+        assert_eq!("Project", task.projects().await.unwrap()[0].title());
+        let subtasks = task.subtasks().await.unwrap();
+        assert_eq!(200, subtasks[0].id());
+        assert_eq!(requests, m.n_requests().await);
+
+        // Hydrating again updates the existing task
+        let mut changed = tasks[0].clone();
+        changed.title = "Changed".to_string();
+        client.hydrate_tasks(vec![changed]);
+        assert_eq!("Changed", task.title());
+    }
+}
@@ -0,0 +1,295 @@
+use crate::search;
+use crate::{projectsbuilder, tasksbuilder};
+use crate::{Cache, Client, Inner, Project, ProjectSnapshot, Task, TaskSnapshot};
+use futures::prelude::*;
+use phabricator_api::maniphest::search::Search as TaskSearch;
+use phabricator_api::project::search::Search as ProjectSearch;
+use phabricator_api::Client as ApiClient;
+use phabricator_api::RequestError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+use url::Url;
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("Cache store IO failure: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Cache store (de)serialization failure: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StoredCache {
+    pub tasks: Vec<TaskSnapshot>,
+    pub projects: Vec<ProjectSnapshot>,
+}
+
+/// Backend to persist the cache of a [`Client`], keyed by the instance url
+pub trait CacheStore: Send + Sync + std::fmt::Debug {
+    fn load(&self, instance: &Url) -> Result<Option<StoredCache>, StoreError>;
+    fn save(&self, instance: &Url, cache: &StoredCache) -> Result<(), StoreError>;
+}
+
+#[derive(Clone, Debug)]
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        FileStore { dir: dir.into() }
+    }
+
+    // Every byte but ASCII alphanumerics, `-` and `.` is escaped as `_XX`,
+    // `_` included, so different instances never share a file
+    fn path(&self, instance: &Url) -> PathBuf {
+        let mut name = String::new();
+        for b in instance.as_str().bytes() {
+            if b.is_ascii_alphanumeric() || b == b'-' || b == b'.' {
+                name.push(b as char);
+            } else {
+                name.push_str(&format!("_{:02X}", b));
+            }
+        }
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+impl CacheStore for FileStore {
+    fn load(&self, instance: &Url) -> Result<Option<StoredCache>, StoreError> {
+        let data = match fs::read(self.path(instance)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(serde_json::from_slice(&data)?))
+    }
+
+    fn save(&self, instance: &Url, cache: &StoredCache) -> Result<(), StoreError> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(instance);
+        // Write to a temporary file first so a failure doesn't leave a
+        // truncated cache behind
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(cache)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+impl Client {
+    /// Create a client and load a previously saved cache from `store`
+    #[cfg(feature = "reqwest")]
+    pub fn with_store(
+        base: Url,
+        token: String,
+        store: Box<dyn CacheStore>,
+    ) -> Result<Self, StoreError> {
+        Self::with_api_client_and_store(ApiClient::new(base, token), store)
+    }
+
+    /// Wrap a low-level client and load a previously saved cache from `store`
+    pub fn with_api_client_and_store(
+        client: ApiClient,
+        store: Box<dyn CacheStore>,
+    ) -> Result<Self, StoreError> {
+        let stored = store.load(client.base())?;
+        let cache = Mutex::new(Cache::new());
+        let inner = Arc::new(Inner {
+            client,
+            cache,
+            store: Some(store),
+        });
+        let client = Client { inner };
+
+        if let Some(stored) = stored {
+            client.hydrate_projects(stored.projects);
+            client.hydrate_tasks(stored.tasks);
+        }
+
+        Ok(client)
+    }
+
+    pub fn save_cache(&self) -> Result<(), StoreError> {
+        let store = match self.inner.store {
+            Some(ref store) => store,
+            None => return Ok(()),
+        };
+
+        let stored = self.access_cache(|cache| StoredCache {
+            tasks: cache.tasks().map(Task::snapshot).collect(),
+            projects: cache.projects.values().map(Project::snapshot).collect(),
+        });
+        store.save(self.client().base(), &stored)
+    }
+
+    pub async fn revalidate_cache(&self) -> Result<(), RequestError> {
+        let (ids, modified, projects) = self.access_cache(|cache| {
+            (
+                cache.tasks().map(Task::id).collect::<Vec<_>>(),
+                cache.tasks().map(Task::modified).min(),
+                cache.projects.keys().cloned().collect::<Vec<_>>(),
+            )
+        });
+
+        if !projects.is_empty() {
+            let mut search: ProjectSearch = Default::default();
+            search.constraints.phids = Some(projects);
+            search::Search::new(
+                self,
+                vec![],
+                Some(search),
+                Box::new(|client, search, cursor| {
+                    projectsbuilder::get(client, search, cursor, true).boxed()
+                }),
+            )
+            .try_for_each(|_| future::ready(Ok(())))
+            .await?;
+        }
+
+        // Only tasks modified since the oldest cached entry can be stale;
+        // project.search has no such constraint so projects are always
+        // fetched again
+        if let Some(modified) = modified {
+            let mut search: TaskSearch = Default::default();
+            search.constraints.ids = Some(ids);
+            search.constraints.modified_start = Some(modified);
+            search.attachments.projects = true;
+            search::Search::new(
+                self,
+                vec![],
+                Some(search),
+                Box::new(|client, search, cursor| {
+                    tasksbuilder::get(client, search, cursor, true).boxed()
+                }),
+            )
+            .try_for_each(|_| future::ready(Ok(())))
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use phabricator_mock::PhabMockServer;
+
+    async fn setup() -> PhabMockServer {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+
+        let p = phabricator_mock::project()
+            .id(10)
+            .name("Project")
+            .build()
+            .unwrap();
+        m.add_project(p.clone());
+
+        let t = phabricator_mock::task()
+            .id(100)
+            .full_name("Test task 100")
+            .description("100 test description")
+            .author(user.clone())
+            .status(m.default_status())
+            .priority(m.default_priority())
+            .projects(vec![p])
+            .date_modified(1000)
+            .build()
+            .unwrap();
+        m.add_task(t);
+        m.new_simple_task(200, &user);
+
+        m
+    }
+
+    fn store(dir: &tempfile::TempDir) -> Box<dyn CacheStore> {
+        Box::new(FileStore::new(dir.path()))
+    }
+
+    #[test]
+    fn paths() {
+        let store = FileStore::new("/cache");
+        let path = |u: &str| store.path(&u.parse().unwrap());
+        assert_eq!(
+            PathBuf::from("/cache/https_3A_2F_2Fa.com_2Fx_5Fy_2F.json"),
+            path("https://a.com/x_y/")
+        );
+        assert_ne!(path("https://a.com/x_y/"), path("https://a.com/x/y/"));
+    }
+
+    #[tokio::test]
+    async fn persist() {
+        let m = setup().await;
+        let dir = tempfile::tempdir().unwrap();
+
+        let client = Client::with_store(m.uri(), m.token().to_string(), store(&dir)).unwrap();
+        let _: Vec<Task> = client
+            .tasks(&[100, 200])
+            .projects()
+            .query()
+            .try_collect()
+            .await
+            .unwrap();
+        client.save_cache().unwrap();
+        let requests = m.n_requests().await;
+
+        let client = Client::with_store(m.uri(), m.token().to_string(), store(&dir)).unwrap();
+        let task = client.cached_task(100).expect("Task not loaded from store");
+        assert_eq!("Test task 100", task.title());
+        assert_eq!("100 test description", task.description());
+
+        let projects = task.projects().await.unwrap();
+        assert_eq!(1, projects.len());
+        assert_eq!(10, projects[0].id());
+        assert!(client.cached_task(200).is_some());
+
+        // Everything should have been served from the loaded cache
+        assert_eq!(requests, m.n_requests().await);
+    }
+
+    #[tokio::test]
+    async fn revalidate() {
+        let m = setup().await;
+        let dir = tempfile::tempdir().unwrap();
+
+        let client = Client::with_store(m.uri(), m.token().to_string(), store(&dir)).unwrap();
+        let _: Vec<Task> = client.tasks(&[100]).query().try_collect().await.unwrap();
+        client.save_cache().unwrap();
+
+        let old = m.get_task(100).unwrap();
+        let t = phabricator_mock::task()
+            .id(100)
+            .phid(old.phid.clone())
+            .full_name("Updated task 100")
+            .description(old.description.clone())
+            .author(old.author.clone())
+            .status(old.status.clone())
+            .priority(old.priority.clone())
+            .date_modified(2000)
+            .build()
+            .unwrap();
+        m.add_task(t);
+
+        let client = Client::with_store(m.uri(), m.token().to_string(), store(&dir)).unwrap();
+        let task = client.cached_task(100).unwrap();
+        assert_eq!("Test task 100", task.title());
+
+        client.revalidate_cache().await.unwrap();
+        assert_eq!("Updated task 100", task.title());
+        assert_eq!(2000, task.modified().timestamp());
+    }
+
+    #[tokio::test]
+    async fn no_store() {
+        let m = setup().await;
+        let client = Client::new(m.uri(), m.token().to_string());
+        let _: Vec<Task> = client.tasks(&[100]).query().try_collect().await.unwrap();
+        client.save_cache().unwrap();
+    }
+}
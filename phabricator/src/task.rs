@@ -1,14 +1,27 @@
+use crate::reference;
+use crate::remarkup;
+#[cfg(feature = "serde")]
+use crate::snapshot::TaskSnapshot;
+use crate::tree::Direction;
+use crate::Column;
 use crate::Project;
+use crate::{Checklist, Comment, Reference};
 use crate::{Client, WeakClient};
+use chrono::{DateTime, Utc};
 use futures::prelude::*;
 use phabricator_api::edge::search::Search as EdgeSearch;
 use phabricator_api::edge::search::Type as EdgeType;
+use phabricator_api::maniphest::search::Boards;
 use phabricator_api::maniphest::search::Projects;
+use phabricator_api::maniphest::search::Search as ManiphestSearch;
 use phabricator_api::maniphest::search::SearchData;
+use phabricator_api::phid::Lookup;
+use phabricator_api::remarkup::process::Context;
+use phabricator_api::transaction::search::Search as TransactionSearch;
+use phabricator_api::transaction::search::SearchCursor as TransactionSearchCursor;
 use phabricator_api::types::Phid;
-use phabricator_api::RequestError;
+use phabricator_api::{ApiRequest, RequestError};
 use rust_decimal::prelude::*;
-use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -25,11 +38,18 @@ pub struct Task {
 struct Inner {
     title: String,
     description: String,
+    // Server rendered description
+    description_html: Option<String>,
     status: String,
+    owner: Option<Phid>,
     projects: Option<Vec<Project>>,
+    columns: Option<Vec<Column>>,
     parents: Option<Vec<Task>>,
     subtasks: Option<Vec<Task>>,
     points: Option<Decimal>,
+    created: DateTime<Utc>,
+    modified: DateTime<Utc>,
+    closed: Option<DateTime<Utc>>,
 }
 
 impl Task {
@@ -41,18 +61,44 @@ impl Task {
             .collect()
     }
 
-    fn from_searchdata(data: SearchData, client: &Client, cache: &HashMap<Phid, Project>) -> Task {
+    fn map_columns(boards: Boards) -> Vec<Column> {
+        boards
+            .boards
+            .into_iter()
+            .flat_map(|(board, columns)| {
+                columns.columns.into_iter().map(move |c| Column {
+                    id: c.id,
+                    phid: c.phid,
+                    name: c.name,
+                    board: board.clone(),
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn from_searchdata(
+        data: SearchData,
+        client: &Client,
+        cache: &HashMap<Phid, Project>,
+    ) -> Task {
         let projects = data
             .attachments
             .projects
             .map(|p| Self::map_projects(p, cache));
+        let columns = data.attachments.columns.map(Self::map_columns);
 
         let inner = Arc::new(Mutex::new(Inner {
             title: data.fields.name,
             description: data.fields.description,
+            description_html: None,
             points: data.fields.points,
             status: data.fields.status.value,
+            owner: data.fields.owner_phid,
+            created: data.fields.created,
+            modified: data.fields.modified,
+            closed: data.fields.closed,
             projects,
+            columns,
             subtasks: None,
             parents: None,
         }));
@@ -70,25 +116,42 @@ impl Task {
             .attachments
             .projects
             .map(|p| Self::map_projects(p, cache));
+        let columns = data.attachments.columns.map(Self::map_columns);
         let mut inner = self.inner.lock().unwrap();
         inner.title = data.fields.name;
-        inner.description = data.fields.description;
+        if inner.description != data.fields.description {
+            inner.description_html = None;
+            inner.description = data.fields.description;
+        }
         inner.points = data.fields.points;
         inner.status = data.fields.status.value;
+        inner.owner = data.fields.owner_phid;
+        inner.created = data.fields.created;
+        inner.modified = data.fields.modified;
+        inner.closed = data.fields.closed;
         inner.projects = projects;
+        // Keep previously fetched columns when they weren't asked for
+        if columns.is_some() {
+            inner.columns = columns;
+        }
         // TODO update other fields
     }
 
-    pub(crate) fn update_from_searchdata(data: SearchData, client: &Client) -> Task {
-        client.update_cache(|cache| match cache.tasks.entry(data.id) {
-            Entry::Vacant(v) => v
-                .insert(Self::from_searchdata(data, client, &cache.projects))
-                .clone(),
-            Entry::Occupied(mut o) => {
-                let t = o.get_mut();
-                t.update_searchdata(data, &cache.projects);
+    pub(crate) fn update_from_searchdata(
+        data: SearchData,
+        client: &Client,
+        projects: &HashMap<Phid, Project>,
+    ) -> Task {
+        client.update_cache(|cache| match cache.task_mut(data.id) {
+            Some(t) => {
+                t.update_searchdata(data, projects);
                 t.clone()
             }
+            None => {
+                let t = Self::from_searchdata(data, client, projects);
+                cache.insert_task(t.clone());
+                t
+            }
         })
     }
 
@@ -110,21 +173,89 @@ impl Task {
         l.description.clone()
     }
 
+    /// Description rendered to HTML by the server. Use
+    /// [`Client::render_descriptions`] to render a whole set of tasks in one
+    /// request.
+    pub async fn description_html(&self) -> Result<String, RequestError> {
+        if let Some(html) = self.rendered_description() {
+            return Ok(html);
+        }
+        let client = self.client.upgrade().unwrap();
+        let description = self.description();
+        let html = client
+            .render_remarkup(Context::Maniphest, vec![description.clone()])
+            .await?
+            .remove(0);
+        self.set_description_html(&description, html.clone());
+        Ok(html)
+    }
+
+    pub(crate) fn rendered_description(&self) -> Option<String> {
+        let l = self.inner.lock().unwrap();
+        l.description_html.clone()
+    }
+
+    // Only stores the html if the description didn't change while it was
+    // being rendered
+    pub(crate) fn set_description_html(&self, description: &str, html: String) {
+        let mut l = self.inner.lock().unwrap();
+        if l.description == description {
+            l.description_html = Some(html);
+        }
+    }
+
     pub fn status(&self) -> String {
         let l = self.inner.lock().unwrap();
         l.status.clone()
     }
 
+    pub fn owner(&self) -> Option<Phid> {
+        let l = self.inner.lock().unwrap();
+        l.owner.clone()
+    }
+
     pub fn points(&self) -> Option<Decimal> {
         let l = self.inner.lock().unwrap();
         l.points
     }
 
+    pub fn created(&self) -> DateTime<Utc> {
+        let l = self.inner.lock().unwrap();
+        l.created
+    }
+
+    pub fn modified(&self) -> DateTime<Utc> {
+        let l = self.inner.lock().unwrap();
+        l.modified
+    }
+
+    pub fn closed(&self) -> Option<DateTime<Utc>> {
+        let l = self.inner.lock().unwrap();
+        l.closed
+    }
+
+    /// Workboard columns the task is in, only known when queried with
+    /// [`TasksBuilder::columns`](crate::tasksbuilder::TasksBuilder::columns)
+    pub fn columns(&self) -> Option<Vec<Column>> {
+        let l = self.inner.lock().unwrap();
+        l.columns.clone()
+    }
+
+    pub(crate) fn columns_resolved(&self) -> bool {
+        let l = self.inner.lock().unwrap();
+        l.columns.is_some()
+    }
+
     pub(crate) fn projects_resolved(&self) -> bool {
         let l = self.inner.lock().unwrap();
         l.projects.is_some()
     }
 
+    pub(crate) fn resolved_projects(&self) -> Option<Vec<Project>> {
+        let l = self.inner.lock().unwrap();
+        l.projects.clone()
+    }
+
     pub async fn projects(&self) -> Result<Vec<Project>, RequestError> {
         {
             let l = self.inner.lock().unwrap();
@@ -133,14 +264,21 @@ impl Task {
             }
         }
         let client = self.client.upgrade().unwrap();
-        client
+        // The query fills in the cached task, which needn't be this one
+        let projects = client
             .tasks(&[self.id])
             .projects()
             .query()
-            .try_for_each(|_| future::ready(Ok(())))
-            .await?;
-        let l = self.inner.lock().unwrap();
-        Ok(l.projects.as_ref().unwrap().clone())
+            .try_filter(|t| future::ready(t.id == self.id))
+            .try_next()
+            .await?
+            .and_then(|t| t.resolved_projects())
+            .ok_or_else(|| RequestError::Incomplete {
+                route: ManiphestSearch::ROUTE.to_string(),
+            })?;
+        let mut l = self.inner.lock().unwrap();
+        l.projects = Some(projects.clone());
+        Ok(projects)
     }
 
     async fn edges(&self, edge: EdgeType) -> Result<Vec<Task>, RequestError> {
@@ -191,4 +329,233 @@ impl Task {
         l.subtasks = Some(tasks);
         Ok(l.subtasks.as_ref().unwrap().clone())
     }
+
+    pub(crate) fn set_related(&self, direction: Direction, tasks: Vec<Task>) {
+        let mut l = self.inner.lock().unwrap();
+        match direction {
+            Direction::Subtasks => l.subtasks = Some(tasks),
+            Direction::Parents => l.parents = Some(tasks),
+        }
+    }
+
+    /// Comments on the task, oldest first. Removed comments are skipped.
+    pub async fn comments(&self) -> Result<Vec<Comment>, RequestError> {
+        // TODO error handle
+        let client = self.client.upgrade().unwrap();
+        let s = TransactionSearch {
+            object: self.phid.0.clone(),
+            ..Default::default()
+        };
+
+        let mut r = client.client().request(&s).await?;
+        let mut data = std::mem::take(&mut r.data);
+        while r.cursor.after.is_some() {
+            let c = TransactionSearchCursor {
+                cursor: &r.cursor,
+                search: &s,
+            };
+            r = client.client().request(&c).await?;
+            data.append(&mut r.data);
+        }
+
+        let mut comments: Vec<_> = data
+            .into_iter()
+            .filter_map(|t| {
+                let created = t.created;
+                let latest = t.comments.into_iter().max_by_key(|c| c.version)?;
+                (!latest.removed).then_some(Comment {
+                    id: latest.id,
+                    author: latest.author,
+                    created,
+                    content: latest.content,
+                })
+            })
+            .collect();
+        comments.sort_by_key(|c| (c.created, c.id));
+        Ok(comments)
+    }
+
+    /// Objects referenced by monogram or phid in the description and
+    /// comments, resolved by the server. References to unknown objects and
+    /// to the task itself are left out.
+    pub async fn references(&self) -> Result<Vec<Reference>, RequestError> {
+        let mut names = reference::scan(&self.description());
+        for comment in self.comments().await? {
+            for name in reference::scan(&comment.content) {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        let own = format!("T{}", self.id);
+        names.retain(|n| *n != own && *n != self.phid.0);
+        if names.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // TODO error handle
+        let client = self.client.upgrade().unwrap();
+        let mut r = client
+            .client()
+            .request(&Lookup {
+                names: names.clone(),
+            })
+            .await?;
+
+        let mut references: Vec<Reference> = Vec::new();
+        for name in names {
+            if let Some(item) = r.0.remove(&name) {
+                if !references.iter().any(|r| r.phid == item.phid) {
+                    references.push(item.into());
+                }
+            }
+        }
+        Ok(references)
+    }
+
+    /// Checklist items in the description
+    pub fn checklist(&self) -> Checklist {
+        Checklist::from_document(&remarkup::parse(&self.description()))
+    }
+
+    /// All tasks below this one in the subtask hierarchy
+    pub async fn descendants(&self) -> Result<Vec<Task>, RequestError> {
+        // TODO error handle
+        let client = self.client.upgrade().unwrap();
+        let tree = client.task_tree(std::iter::once(self)).query().await?;
+        Ok(tree.tasks().skip(1).cloned().collect())
+    }
+
+    /// All tasks above this one in the subtask hierarchy
+    pub async fn ancestors(&self) -> Result<Vec<Task>, RequestError> {
+        // TODO error handle
+        let client = self.client.upgrade().unwrap();
+        let tree = client
+            .task_tree(std::iter::once(self))
+            .parents()
+            .query()
+            .await?;
+        Ok(tree.tasks().skip(1).cloned().collect())
+    }
+}
+
+impl Task {
+    // Local updates for edits made through the API, avoiding a refetch
+
+    pub(crate) fn set_status(&self, status: String) {
+        let mut l = self.inner.lock().unwrap();
+        l.status = status;
+    }
+
+    pub(crate) fn set_owner(&self, owner: Option<Phid>) {
+        let mut l = self.inner.lock().unwrap();
+        l.owner = owner;
+    }
+
+    pub(crate) fn set_points(&self, points: Option<Decimal>) {
+        let mut l = self.inner.lock().unwrap();
+        l.points = points;
+    }
+
+    /// Have the projects resolved again on the next access
+    pub(crate) fn forget_projects(&self) {
+        let mut l = self.inner.lock().unwrap();
+        l.projects = None;
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Task {
+    // Only keep the project list if all of them are known, otherwise they'll
+    // get resolved on demand again
+    fn snapshot_projects(
+        projects: Option<Vec<Phid>>,
+        cache: &HashMap<Phid, Project>,
+    ) -> Option<Vec<Project>> {
+        projects.and_then(|projects| {
+            projects
+                .iter()
+                .map(|phid| cache.get(phid).cloned())
+                .collect::<Option<Vec<_>>>()
+        })
+    }
+
+    pub(crate) fn from_snapshot(
+        snapshot: TaskSnapshot,
+        client: &Client,
+        cache: &HashMap<Phid, Project>,
+    ) -> Task {
+        let inner = Arc::new(Mutex::new(Inner {
+            title: snapshot.title,
+            description: snapshot.description,
+            description_html: None,
+            status: snapshot.status,
+            owner: snapshot.owner,
+            points: snapshot.points,
+            created: snapshot.created,
+            modified: snapshot.modified,
+            closed: snapshot.closed,
+            projects: Self::snapshot_projects(snapshot.projects, cache),
+            columns: snapshot.columns,
+            subtasks: None,
+            parents: None,
+        }));
+
+        Task {
+            id: snapshot.id,
+            phid: snapshot.phid,
+            client: client.downgrade(),
+            inner,
+        }
+    }
+
+    pub(crate) fn update_snapshot(
+        &mut self,
+        snapshot: TaskSnapshot,
+        cache: &HashMap<Phid, Project>,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.title = snapshot.title;
+        if inner.description != snapshot.description {
+            inner.description_html = None;
+            inner.description = snapshot.description;
+        }
+        inner.status = snapshot.status;
+        inner.owner = snapshot.owner;
+        inner.points = snapshot.points;
+        inner.created = snapshot.created;
+        inner.modified = snapshot.modified;
+        inner.closed = snapshot.closed;
+        inner.projects = Self::snapshot_projects(snapshot.projects, cache);
+        inner.columns = snapshot.columns;
+    }
+
+    pub fn snapshot(&self) -> TaskSnapshot {
+        let l = self.inner.lock().unwrap();
+        let phids = |tasks: &Option<Vec<Task>>| {
+            tasks
+                .as_ref()
+                .map(|tasks| tasks.iter().map(|t| t.phid.clone()).collect())
+        };
+
+        TaskSnapshot {
+            id: self.id,
+            phid: self.phid.clone(),
+            title: l.title.clone(),
+            description: l.description.clone(),
+            status: l.status.clone(),
+            owner: l.owner.clone(),
+            points: l.points,
+            created: l.created,
+            modified: l.modified,
+            closed: l.closed,
+            projects: l
+                .projects
+                .as_ref()
+                .map(|projects| projects.iter().map(|p| p.phid().clone()).collect()),
+            columns: l.columns.clone(),
+            parents: phids(&l.parents),
+            subtasks: phids(&l.subtasks),
+        }
+    }
 }
@@ -1,5 +1,6 @@
 use crate::search;
 use crate::Client;
+use crate::Project;
 use crate::Task;
 use futures::prelude::*;
 use phabricator_api::maniphest::search::Search;
@@ -7,13 +8,14 @@ use phabricator_api::maniphest::search::SearchCursor;
 use phabricator_api::types::Cursor;
 use phabricator_api::types::Phid;
 use phabricator_api::RequestError;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
-async fn get(
+pub(crate) async fn get(
     client: &Client,
     search: Arc<Search>,
     cursor: Option<Cursor>,
+    cache: bool,
 ) -> Result<search::QueryData<Task>, RequestError> {
     let mut data = match cursor {
         Some(cursor) => {
@@ -32,18 +34,30 @@ async fn get(
         .flatten()
         .collect();
 
-    if !projects.is_empty() {
-        client
-            .projects_by_phid(projects)
+    let projects: HashMap<Phid, Project> = if projects.is_empty() {
+        HashMap::new()
+    } else {
+        let mut builder = client.projects_by_phid(projects);
+        if !cache {
+            builder = builder.uncached();
+        }
+        builder
             .query()
-            .try_for_each(|_| future::ready(Ok(())))
-            .await?;
-    }
+            .map_ok(|p| (p.phid().clone(), p))
+            .try_collect()
+            .await?
+    };
 
     let tasks = data
         .data
         .drain(..)
-        .map(|d| Task::update_from_searchdata(d, client))
+        .map(|d| {
+            if cache {
+                Task::update_from_searchdata(d, client, &projects)
+            } else {
+                Task::from_searchdata(d, client, &projects)
+            }
+        })
         .collect();
 
     let cursor = if data.cursor.after.is_some() {
@@ -64,6 +78,8 @@ pub struct TasksBuilder<'c> {
     client: &'c Client,
     constraints: Constraint<'c>,
     resolve_projects: bool,
+    resolve_columns: bool,
+    cache: bool,
 }
 
 impl<'a, 'c> TasksBuilder<'c> {
@@ -72,6 +88,8 @@ impl<'a, 'c> TasksBuilder<'c> {
             client,
             constraints: Constraint::Tasks(tasks.copied().collect()),
             resolve_projects: false,
+            resolve_columns: false,
+            cache: true,
         }
     }
 
@@ -83,6 +101,8 @@ impl<'a, 'c> TasksBuilder<'c> {
             client,
             constraints: Constraint::Phids(phids.collect()),
             resolve_projects: false,
+            resolve_columns: false,
+            cache: true,
         }
     }
 
@@ -91,9 +111,23 @@ impl<'a, 'c> TasksBuilder<'c> {
         self
     }
 
+    pub fn columns(mut self) -> Self {
+        self.resolve_columns = true;
+        self
+    }
+
+    /// Always retrieve the tasks from the server and don't store them in
+    /// the cache
+    pub fn uncached(mut self) -> Self {
+        self.cache = false;
+        self
+    }
+
     pub fn query(self) -> impl Stream<Item = Result<Task, RequestError>> + 'c {
         let client = &self.client;
         let resolve_projects = self.resolve_projects;
+        let resolve_columns = self.resolve_columns;
+        let cache = self.cache;
 
         let mut constraints = self.constraints;
         let (search, cached) = match constraints {
@@ -102,13 +136,11 @@ impl<'a, 'c> TasksBuilder<'c> {
                     tasks
                         .iter()
                         .fold((vec![], vec![]), |(mut lookup, mut cached), t| {
-                            let task = client.cached_task(*t).filter(|t| {
-                                if resolve_projects {
-                                    t.projects_resolved()
-                                } else {
-                                    true
-                                }
-                            });
+                            let task =
+                                cache.then(|| client.cached_task(*t)).flatten().filter(|t| {
+                                    (!resolve_projects || t.projects_resolved())
+                                        && (!resolve_columns || t.columns_resolved())
+                                });
 
                             match task {
                                 Some(t) => cached.push(t),
@@ -122,6 +154,7 @@ impl<'a, 'c> TasksBuilder<'c> {
                     let mut search: Search = Default::default();
                     search.constraints.ids = Some(lookup);
                     search.attachments.projects = self.resolve_projects;
+                    search.attachments.columns = self.resolve_columns;
                     Some(search)
                 };
                 (search, cached)
@@ -131,13 +164,13 @@ impl<'a, 'c> TasksBuilder<'c> {
                     phids
                         .iter()
                         .fold((vec![], vec![]), |(mut lookup, mut cached), p| {
-                            let task = client.cached_task_by_phid(p).filter(|t| {
-                                if resolve_projects {
-                                    t.projects_resolved()
-                                } else {
-                                    true
-                                }
-                            });
+                            let task = cache
+                                .then(|| client.cached_task_by_phid(p))
+                                .flatten()
+                                .filter(|t| {
+                                    (!resolve_projects || t.projects_resolved())
+                                        && (!resolve_columns || t.columns_resolved())
+                                });
 
                             match task {
                                 Some(t) => cached.push(t),
@@ -151,6 +184,7 @@ impl<'a, 'c> TasksBuilder<'c> {
                     let mut search: Search = Default::default();
                     search.constraints.phids = Some(lookup);
                     search.attachments.projects = self.resolve_projects;
+                    search.attachments.columns = self.resolve_columns;
                     Some(search)
                 };
                 (search, cached)
@@ -161,7 +195,7 @@ impl<'a, 'c> TasksBuilder<'c> {
             self.client,
             cached,
             search,
-            Box::new(|client, search, cursor| get(client, search, cursor).boxed()),
+            Box::new(move |client, search, cursor| get(client, search, cursor, cache).boxed()),
         )
     }
 }
@@ -0,0 +1,511 @@
+use crate::{Client, Project, Task};
+use futures::prelude::*;
+use phabricator_api::maniphest::edit::{Edit, Transaction};
+use phabricator_api::phid::Lookup;
+use phabricator_api::types::Phid;
+use phabricator_api::RequestError;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("Template IO failure: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid YAML template: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("Invalid TOML template: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("Unknown template format, expected .yaml, .yml or .toml")]
+    UnknownFormat,
+    #[error("Undefined template variable: {0}")]
+    UnknownVariable(String),
+    #[error("Unknown project: #{0}")]
+    UnknownProject(String),
+    #[error("Unknown user: @{0}")]
+    UnknownUser(String),
+    #[error("Request failure: {0}")]
+    Request(#[from] RequestError),
+    /// Creating the tasks failed part way; the tasks in `created` do exist
+    #[error("Failed after creating {} tasks: {source}", created.len())]
+    Incomplete {
+        created: Vec<u32>,
+        source: RequestError,
+    },
+}
+
+fn deserialize_points<'de, D>(d: D) -> Result<Option<Decimal>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Points {
+        Integer(i64),
+        Float(f64),
+        String(String),
+    }
+    let points = match Option::<Points>::deserialize(d)? {
+        None => return Ok(None),
+        Some(Points::Integer(i)) => Decimal::from(i),
+        Some(Points::Float(f)) => {
+            Decimal::from_str(&f.to_string()).map_err(serde::de::Error::custom)?
+        }
+        Some(Points::String(s)) => Decimal::from_str(&s).map_err(serde::de::Error::custom)?,
+    };
+    Ok(Some(points))
+}
+
+/// Tree of tasks to create. Titles and descriptions can refer to variables
+/// as `${name}`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Template {
+    /// Default values for variables
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    pub tasks: Vec<TaskTemplate>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TaskTemplate {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    /// Project slugs
+    #[serde(default)]
+    pub projects: Vec<String>,
+    /// Username of the owner
+    pub owner: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_points")]
+    pub points: Option<Decimal>,
+    #[serde(default)]
+    pub subtasks: Vec<TaskTemplate>,
+}
+
+impl Template {
+    pub fn from_yaml(spec: &str) -> Result<Self, TemplateError> {
+        Ok(serde_yaml::from_str(spec)?)
+    }
+
+    pub fn from_toml(spec: &str) -> Result<Self, TemplateError> {
+        Ok(toml::from_str(spec)?)
+    }
+
+    /// Load a template, picking the format based on the file extension
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, TemplateError> {
+        let path = path.as_ref();
+        let spec = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml") | Some("yml") => Self::from_yaml(&spec),
+            Some("toml") => Self::from_toml(&spec),
+            _ => Err(TemplateError::UnknownFormat),
+        }
+    }
+}
+
+fn substitute(text: &str, variables: &HashMap<String, String>) -> Result<String, TemplateError> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = match after.find('}') {
+            Some(end) => end,
+            None => {
+                // Unterminated, keep as is
+                out.push_str(&rest[start..]);
+                return Ok(out);
+            }
+        };
+        let name = after[..end].trim();
+        let value = variables
+            .get(name)
+            .ok_or_else(|| TemplateError::UnknownVariable(name.to_string()))?;
+        out.push_str(value);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Task of a [`Plan`], with variables substituted and projects and owner
+/// resolved
+#[derive(Clone, Debug)]
+pub struct PlannedTask {
+    pub title: String,
+    pub description: String,
+    pub projects: Vec<Project>,
+    /// Username and phid of the owner
+    pub owner: Option<(String, Phid)>,
+    pub points: Option<Decimal>,
+    pub subtasks: Vec<PlannedTask>,
+}
+
+impl PlannedTask {
+    fn transactions(&self, parent: Option<&Phid>) -> Vec<Transaction> {
+        let mut transactions = vec![Transaction::Title(self.title.clone())];
+        if !self.description.is_empty() {
+            transactions.push(Transaction::Description(self.description.clone()));
+        }
+        if !self.projects.is_empty() {
+            let phids = self.projects.iter().map(|p| p.phid().clone()).collect();
+            transactions.push(Transaction::AddProjects(phids));
+        }
+        if let Some((_, owner)) = &self.owner {
+            transactions.push(Transaction::Owner(Some(owner.clone())));
+        }
+        if let Some(points) = self.points {
+            transactions.push(Transaction::Points(Some(points)));
+        }
+        if let Some(parent) = parent {
+            transactions.push(Transaction::AddParents(vec![parent.clone()]));
+        }
+        transactions
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let mut details = Vec::new();
+        for p in &self.projects {
+            details.push(format!("#{}", p.slug().unwrap_or_else(|| p.title())));
+        }
+        if let Some((name, _)) = &self.owner {
+            details.push(format!("@{}", name));
+        }
+        if let Some(points) = self.points {
+            details.push(format!("{} points", points));
+        }
+
+        write!(f, "{}- {}", "  ".repeat(depth), self.title)?;
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        writeln!(f)?;
+        for s in &self.subtasks {
+            s.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Validated template, ready to be created. Its `Display` implementation
+/// shows the tasks that would be created.
+#[derive(Clone, Debug)]
+pub struct Plan {
+    pub tasks: Vec<PlannedTask>,
+}
+
+impl Plan {
+    /// Number of tasks that will be created
+    pub fn len(&self) -> usize {
+        fn count(tasks: &[PlannedTask]) -> usize {
+            tasks.iter().map(|t| 1 + count(&t.subtasks)).sum()
+        }
+        count(&self.tasks)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Create all tasks, parents before their subtasks. Returns the created
+    /// tasks in the same (depth first) order as the plan.
+    pub async fn create(&self, client: &Client) -> Result<Vec<Task>, TemplateError> {
+        let mut created = Vec::new();
+        let mut pending: Vec<(&PlannedTask, Option<Phid>)> =
+            self.tasks.iter().rev().map(|t| (t, None)).collect();
+
+        while let Some((task, parent)) = pending.pop() {
+            let e = Edit {
+                object: None,
+                transactions: task.transactions(parent.as_ref()),
+            };
+            let r = match client.client().request(&e).await {
+                Ok(r) => r,
+                Err(source) => return Err(TemplateError::Incomplete { created, source }),
+            };
+            created.push(r.object.id);
+            pending.extend(
+                task.subtasks
+                    .iter()
+                    .rev()
+                    .map(|s| (s, Some(r.object.phid.clone()))),
+            );
+        }
+
+        // The tasks exist at this point, so don't lose track of them either
+        let tasks = client
+            .tasks(&created)
+            .query()
+            .map_ok(|t| (t.id(), t))
+            .try_collect::<HashMap<u32, Task>>()
+            .await;
+        let mut tasks = match tasks {
+            Ok(tasks) => tasks,
+            Err(source) => return Err(TemplateError::Incomplete { created, source }),
+        };
+        Ok(created.iter().filter_map(|id| tasks.remove(id)).collect())
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for t in &self.tasks {
+            t.fmt_indented(f, 0)?;
+        }
+        Ok(())
+    }
+}
+
+fn collect_names(tasks: &[TaskTemplate], names: &mut Vec<String>) {
+    for t in tasks {
+        for slug in &t.projects {
+            names.push(format!("#{}", slug));
+        }
+        if let Some(owner) = &t.owner {
+            names.push(format!("@{}", owner));
+        }
+        collect_names(&t.subtasks, names);
+    }
+}
+
+impl Client {
+    /// Substitute `variables` (on top of the template defaults) and resolve
+    /// project slugs and usernames, without creating anything
+    pub async fn plan_template(
+        &self,
+        template: &Template,
+        variables: &HashMap<String, String>,
+    ) -> Result<Plan, TemplateError> {
+        let mut vars = template.variables.clone();
+        vars.extend(variables.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        let mut names = Vec::new();
+        collect_names(&template.tasks, &mut names);
+        names.sort();
+        names.dedup();
+        let mut lookup = if names.is_empty() {
+            HashMap::new()
+        } else {
+            self.client()
+                .request(&Lookup {
+                    names: names.clone(),
+                })
+                .await?
+                .0
+        };
+
+        let mut phids = HashMap::new();
+        for name in names {
+            match lookup.remove(&name) {
+                Some(item) => {
+                    phids.insert(name, item.phid);
+                }
+                None => match name.strip_prefix('#') {
+                    Some(slug) => return Err(TemplateError::UnknownProject(slug.to_string())),
+                    None => return Err(TemplateError::UnknownUser(name[1..].to_string())),
+                },
+            }
+        }
+
+        let project_phids: Vec<&Phid> = phids
+            .iter()
+            .filter(|(name, _)| name.starts_with('#'))
+            .map(|(_, phid)| phid)
+            .collect();
+        let projects: HashMap<Phid, Project> = self
+            .projects_by_phid(project_phids)
+            .query()
+            .map_ok(|p| (p.phid().clone(), p))
+            .try_collect()
+            .await?;
+
+        fn plan(
+            tasks: &[TaskTemplate],
+            vars: &HashMap<String, String>,
+            phids: &HashMap<String, Phid>,
+            projects: &HashMap<Phid, Project>,
+        ) -> Result<Vec<PlannedTask>, TemplateError> {
+            tasks
+                .iter()
+                .map(|t| {
+                    Ok(PlannedTask {
+                        title: substitute(&t.title, vars)?,
+                        description: substitute(&t.description, vars)?,
+                        projects: t
+                            .projects
+                            .iter()
+                            .map(|slug| {
+                                phids
+                                    .get(&format!("#{}", slug))
+                                    .and_then(|phid| projects.get(phid))
+                                    .cloned()
+                                    .ok_or_else(|| TemplateError::UnknownProject(slug.clone()))
+                            })
+                            .collect::<Result<_, _>>()?,
+                        owner: t
+                            .owner
+                            .as_ref()
+                            .map(|name| (name.clone(), phids[&format!("@{}", name)].clone())),
+                        points: t.points,
+                        subtasks: plan(&t.subtasks, vars, phids, projects)?,
+                    })
+                })
+                .collect()
+        }
+
+        Ok(Plan {
+            tasks: plan(&template.tasks, &vars, &phids, &projects)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "reqwest")]
+    use phabricator_mock::PhabMockServer;
+
+    const YAML: &str = r#"
+variables:
+  version: "0.1"
+tasks:
+  - title: Release ${version}
+    description: Tracking task for ${ version }
+    projects: [release]
+    owner: user
+    points: 1.5
+    subtasks:
+      - title: Changelog for ${version}
+        points: 1
+      - title: Tag ${version}
+        owner: user
+        subtasks:
+          - title: Announce
+  - title: Retrospective
+"#;
+
+    const TOML: &str = r#"
+[[tasks]]
+title = "Release ${version}"
+projects = ["release"]
+
+[[tasks.subtasks]]
+title = "Changelog"
+points = "2"
+"#;
+
+    #[cfg(feature = "reqwest")]
+    async fn setup() -> PhabMockServer {
+        let m = PhabMockServer::start().await;
+        m.new_user("user", "Test User");
+        let p = phabricator_mock::project()
+            .id(10)
+            .name("Release")
+            .slug("release".to_string())
+            .build()
+            .unwrap();
+        m.add_project(p);
+        m
+    }
+
+    #[test]
+    fn parse() {
+        let t = Template::from_yaml(YAML).unwrap();
+        assert_eq!(2, t.tasks.len());
+        assert_eq!(Some(Decimal::new(15, 1)), t.tasks[0].points);
+        assert_eq!("Announce", t.tasks[0].subtasks[1].subtasks[0].title);
+
+        let t = Template::from_toml(TOML).unwrap();
+        assert_eq!(vec!["release"], t.tasks[0].projects);
+        assert_eq!(Some(Decimal::new(2, 0)), t.tasks[0].subtasks[0].points);
+    }
+
+    #[test]
+    fn substitution() {
+        let vars: HashMap<_, _> = vec![("a".to_string(), "1".to_string())]
+            .into_iter()
+            .collect();
+        assert_eq!("x1y1 ${", substitute("x${a}y${ a } ${", &vars).unwrap());
+        assert!(matches!(
+            substitute("${b}", &vars),
+            Err(TemplateError::UnknownVariable(v)) if v == "b"
+        ));
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn plan() {
+        let m = setup().await;
+        let client = Client::new(m.uri(), m.token().to_string());
+        let template = Template::from_yaml(YAML).unwrap();
+
+        let mut vars = HashMap::new();
+        vars.insert("version".to_string(), "1.0".to_string());
+        let requests = m.n_requests().await;
+        let plan = client.plan_template(&template, &vars).await.unwrap();
+        assert_eq!(5, plan.len());
+        assert_eq!(
+            "- Release 1.0 (#release, @user, 1.5 points)\n\
+             \x20 - Changelog for 1.0 (1 points)\n\
+             \x20 - Tag 1.0 (@user)\n\
+             \x20   - Announce\n\
+             - Retrospective\n",
+            plan.to_string()
+        );
+        assert_eq!("Tracking task for 1.0", plan.tasks[0].description);
+        // Only lookups, nothing created
+        assert_eq!(requests + 2, m.n_requests().await);
+        assert!(m.get_task(1).is_none());
+
+        let mut template = template;
+        template.tasks[1].owner = Some("nobody".to_string());
+        assert!(matches!(
+            client.plan_template(&template, &vars).await,
+            Err(TemplateError::UnknownUser(u)) if u == "nobody"
+        ));
+        template.tasks[1].projects = vec!["missing".to_string()];
+        assert!(matches!(
+            client.plan_template(&template, &vars).await,
+            Err(TemplateError::UnknownProject(p)) if p == "missing"
+        ));
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn create() {
+        let m = setup().await;
+        let client = Client::new(m.uri(), m.token().to_string());
+        let template = Template::from_yaml(YAML).unwrap();
+        let plan = client
+            .plan_template(&template, &HashMap::new())
+            .await
+            .unwrap();
+
+        let tasks = plan.create(&client).await.unwrap();
+        let titles: Vec<_> = tasks.iter().map(|t| t.title()).collect();
+        assert_eq!(
+            vec![
+                "Release 0.1",
+                "Changelog for 0.1",
+                "Tag 0.1",
+                "Announce",
+                "Retrospective"
+            ],
+            titles
+        );
+        assert_eq!(Some(Decimal::new(15, 1)), tasks[0].points());
+        assert!(tasks[0].owner().is_some());
+        assert_eq!("Release", tasks[0].projects().await.unwrap()[0].title());
+
+        let subtasks = tasks[0].subtasks().await.unwrap();
+        let mut ids: Vec<_> = subtasks.iter().map(|t| t.id()).collect();
+        ids.sort_unstable();
+        assert_eq!(vec![tasks[1].id(), tasks[2].id()], ids);
+        assert_eq!(tasks[3].id(), tasks[2].subtasks().await.unwrap()[0].id());
+        assert!(tasks[4].parents().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,446 @@
+use crate::Client;
+use crate::Task;
+use futures::prelude::*;
+use phabricator_api::edge::search::Search as EdgeSearch;
+use phabricator_api::edge::search::SearchCursor as EdgeSearchCursor;
+use phabricator_api::edge::search::Type as EdgeType;
+use phabricator_api::types::Phid;
+use phabricator_api::RequestError;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Subtasks,
+    Parents,
+}
+
+impl Direction {
+    fn edge(self) -> EdgeType {
+        match self {
+            Direction::Subtasks => EdgeType::TaskSubtask,
+            Direction::Parents => EdgeType::TaskParent,
+        }
+    }
+}
+
+async fn edges(
+    client: &Client,
+    sources: Vec<Phid>,
+    direction: Direction,
+) -> Result<Vec<(Phid, Phid)>, RequestError> {
+    let search = EdgeSearch {
+        sources,
+        types: vec![direction.edge()],
+        ..Default::default()
+    };
+
+    let mut r = client.client().request(&search).await?;
+    let mut edges: Vec<_> = r.data.drain(..).map(|d| (d.source, d.dest)).collect();
+    while r.cursor.after.is_some() {
+        let c = EdgeSearchCursor {
+            cursor: &r.cursor,
+            search: &search,
+        };
+        r = client.client().request(&c).await?;
+        edges.extend(r.data.drain(..).map(|d| (d.source, d.dest)));
+    }
+
+    Ok(edges)
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    task: Task,
+    depth: u32,
+    children: Vec<Phid>,
+}
+
+/// Tasks reachable from a set of roots by following either subtask or
+/// parent edges
+#[derive(Clone, Debug)]
+pub struct TaskTree {
+    direction: Direction,
+    roots: Vec<Phid>,
+    // Phids in breadth-first order
+    order: Vec<Phid>,
+    nodes: HashMap<Phid, Node>,
+    cycles: Vec<(Phid, Phid)>,
+}
+
+impl TaskTree {
+    fn find_cycles(&mut self) {
+        fn visit(
+            phid: &Phid,
+            nodes: &HashMap<Phid, Node>,
+            path: &mut Vec<Phid>,
+            done: &mut HashSet<Phid>,
+            cycles: &mut Vec<(Phid, Phid)>,
+        ) {
+            path.push(phid.clone());
+            for c in &nodes[phid].children {
+                if path.contains(c) {
+                    cycles.push((phid.clone(), c.clone()));
+                } else if !done.contains(c) {
+                    visit(c, nodes, path, done, cycles);
+                }
+            }
+            path.pop();
+            done.insert(phid.clone());
+        }
+
+        let mut done = HashSet::new();
+        let mut cycles = Vec::new();
+        for r in &self.roots {
+            if !done.contains(r) {
+                visit(r, &self.nodes, &mut Vec::new(), &mut done, &mut cycles);
+            }
+        }
+        self.cycles = cycles;
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    pub fn roots(&self) -> Vec<Task> {
+        self.roots
+            .iter()
+            .map(|p| self.nodes[p].task.clone())
+            .collect()
+    }
+
+    /// All tasks in the tree in breadth-first order, including the roots
+    pub fn tasks(&self) -> impl Iterator<Item = &Task> {
+        self.order.iter().map(move |p| &self.nodes[p].task)
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    pub fn contains(&self, task: &Task) -> bool {
+        self.nodes.contains_key(task.phid())
+    }
+
+    /// Distance of the task from the closest root
+    pub fn depth(&self, task: &Task) -> Option<u32> {
+        self.nodes.get(task.phid()).map(|n| n.depth)
+    }
+
+    /// Tasks one step further from the roots; empty for tasks at the
+    /// maximum depth as those weren't expanded
+    pub fn children(&self, task: &Task) -> Vec<Task> {
+        self.nodes
+            .get(task.phid())
+            .map(|n| {
+                n.children
+                    .iter()
+                    .map(|c| self.nodes[c].task.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Edges that point back to a task earlier on the same path
+    pub fn cycles(&self) -> Vec<(Task, Task)> {
+        self.cycles
+            .iter()
+            .map(|(from, to)| (self.nodes[from].task.clone(), self.nodes[to].task.clone()))
+            .collect()
+    }
+
+    /// Depth-first walk from each root giving the nesting level of every
+    /// visit. Tasks reachable over multiple paths are visited once per path,
+    /// edges closing a cycle are not followed.
+    pub fn walk(&self) -> Vec<(usize, Task)> {
+        fn visit(tree: &TaskTree, phid: &Phid, path: &mut Vec<Phid>, out: &mut Vec<(usize, Task)>) {
+            out.push((path.len(), tree.nodes[phid].task.clone()));
+            path.push(phid.clone());
+            for c in &tree.nodes[phid].children {
+                if !path.contains(c) {
+                    visit(tree, c, path, out);
+                }
+            }
+            path.pop();
+        }
+
+        let mut out = Vec::new();
+        for r in &self.roots {
+            visit(self, r, &mut Vec::new(), &mut out);
+        }
+        out
+    }
+}
+
+impl fmt::Display for TaskTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (level, task) in self.walk() {
+            writeln!(
+                f,
+                "{:indent$}T{} {}",
+                "",
+                task.id(),
+                task.title(),
+                indent = level * 2
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub struct TaskTreeBuilder<'c> {
+    client: &'c Client,
+    roots: Vec<Task>,
+    direction: Direction,
+    max_depth: Option<u32>,
+}
+
+impl<'c> TaskTreeBuilder<'c> {
+    pub(crate) fn new(client: &'c Client, roots: Vec<Task>) -> Self {
+        TaskTreeBuilder {
+            client,
+            roots,
+            direction: Direction::Subtasks,
+            max_depth: None,
+        }
+    }
+
+    /// Follow parent rather than subtask edges
+    pub fn parents(mut self) -> Self {
+        self.direction = Direction::Parents;
+        self
+    }
+
+    pub fn max_depth(mut self, depth: u32) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    pub async fn query(self) -> Result<TaskTree, RequestError> {
+        let client = self.client;
+        let direction = self.direction;
+
+        let mut tree = TaskTree {
+            direction,
+            roots: Vec::new(),
+            order: Vec::new(),
+            nodes: HashMap::new(),
+            cycles: Vec::new(),
+        };
+
+        for task in self.roots {
+            if tree.nodes.contains_key(task.phid()) {
+                continue;
+            }
+            tree.roots.push(task.phid().clone());
+            tree.order.push(task.phid().clone());
+            tree.nodes.insert(
+                task.phid().clone(),
+                Node {
+                    task,
+                    depth: 0,
+                    children: Vec::new(),
+                },
+            );
+        }
+
+        let mut frontier = tree.roots.clone();
+        let mut depth = 0;
+        while !frontier.is_empty() && Some(depth) != self.max_depth {
+            // One edge and one task search for the whole level
+            let edges = edges(client, frontier.clone(), direction).await?;
+
+            let mut unknown: Vec<_> = edges
+                .iter()
+                .map(|(_, dest)| dest)
+                .filter(|dest| !tree.nodes.contains_key(dest))
+                .collect();
+            unknown.sort_by(|a, b| a.0.cmp(&b.0));
+            unknown.dedup();
+
+            let found: HashMap<Phid, Task> = if unknown.is_empty() {
+                HashMap::new()
+            } else {
+                let mut phids = unknown.iter().copied();
+                client
+                    .tasks_by_phid(&mut phids)
+                    .query()
+                    .map_ok(|t| (t.phid().clone(), t))
+                    .try_collect()
+                    .await?
+            };
+
+            let mut next = Vec::new();
+            for p in unknown {
+                // Tasks the user can't see won't be returned
+                if let Some(task) = found.get(p) {
+                    tree.order.push(p.clone());
+                    tree.nodes.insert(
+                        p.clone(),
+                        Node {
+                            task: task.clone(),
+                            depth: depth + 1,
+                            children: Vec::new(),
+                        },
+                    );
+                    next.push(p.clone());
+                }
+            }
+
+            for (source, dest) in edges {
+                if tree.nodes.contains_key(&dest) {
+                    let node = tree.nodes.get_mut(&source).unwrap();
+                    if !node.children.contains(&dest) {
+                        node.children.push(dest);
+                    }
+                }
+            }
+
+            for p in &frontier {
+                let node = &tree.nodes[p];
+                let related = node
+                    .children
+                    .iter()
+                    .map(|c| tree.nodes[c].task.clone())
+                    .collect();
+                node.task.set_related(direction, related);
+            }
+
+            frontier = next;
+            depth += 1;
+        }
+
+        tree.find_cycles();
+        Ok(tree)
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use phabricator_mock::task;
+    use phabricator_mock::PhabMockServer;
+
+    // T100 -> T200 -> {T300, T400}, T100 -> T300
+    async fn setup() -> (PhabMockServer, Client) {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+
+        let t100 = m.new_simple_task(100, &user);
+        let t200 = m.new_simple_task(200, &user);
+        let t300 = m.new_simple_task(300, &user);
+        let t400 = m.new_simple_task(400, &user);
+        task::link(&t100, &t200);
+        task::link(&t200, &t300);
+        task::link(&t200, &t400);
+        task::link(&t100, &t300);
+
+        let client = Client::new(m.uri(), m.token().to_string());
+        (m, client)
+    }
+
+    async fn get_task(client: &Client, id: u32) -> Task {
+        let mut tasks: Vec<Task> = client.tasks(&[id]).query().try_collect().await.unwrap();
+        tasks.pop().unwrap()
+    }
+
+    fn ids<'a, I: IntoIterator<Item = &'a Task>>(tasks: I) -> Vec<u32> {
+        let mut ids: Vec<_> = tasks.into_iter().map(Task::id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn subtasks() {
+        let (m, client) = setup().await;
+        let root = get_task(&client, 100).await;
+
+        let tree = client
+            .task_tree(std::iter::once(&root))
+            .query()
+            .await
+            .unwrap();
+        // One edge search per level and one task search per level with new
+        // tasks on top of the initial task query
+        assert_eq!(1 + 5, m.n_requests().await);
+
+        assert_eq!(4, tree.len());
+        assert_eq!(&[100, 200, 300, 400], ids(tree.tasks()).as_slice());
+        assert!(tree.cycles().is_empty());
+
+        let t200 = client.cached_task(200).unwrap();
+        let t300 = client.cached_task(300).unwrap();
+        assert_eq!(Some(0), tree.depth(&root));
+        assert_eq!(Some(1), tree.depth(&t200));
+        assert_eq!(Some(1), tree.depth(&t300));
+        assert_eq!(&[200, 300], ids(&tree.children(&root)).as_slice());
+        assert_eq!(&[300, 400], ids(&tree.children(&t200)).as_slice());
+
+        // The subtasks learned while walking are remembered
+        let requests = m.n_requests().await;
+        assert_eq!(2, t200.subtasks().await.unwrap().len());
+        assert_eq!(requests, m.n_requests().await);
+
+        let walk: Vec<_> = tree.walk().iter().map(|(l, t)| (*l, t.id())).collect();
+        assert_eq!(5, walk.len());
+        assert_eq!((0, 100), walk[0]);
+    }
+
+    #[tokio::test]
+    async fn max_depth() {
+        let (_m, client) = setup().await;
+        let root = get_task(&client, 100).await;
+
+        let tree = client
+            .task_tree(std::iter::once(&root))
+            .max_depth(1)
+            .query()
+            .await
+            .unwrap();
+        assert_eq!(&[100, 200, 300], ids(tree.tasks()).as_slice());
+
+        let t200 = client.cached_task(200).unwrap();
+        assert!(tree.children(&t200).is_empty());
+    }
+
+    #[tokio::test]
+    async fn ancestors() {
+        let (_m, client) = setup().await;
+        let t400 = get_task(&client, 400).await;
+
+        let ancestors = t400.ancestors().await.unwrap();
+        assert_eq!(&[100, 200], ids(&ancestors).as_slice());
+
+        let t100 = client.cached_task(100).unwrap();
+        let descendants = t100.descendants().await.unwrap();
+        assert_eq!(&[200, 300, 400], ids(&descendants).as_slice());
+    }
+
+    #[tokio::test]
+    async fn cycle() {
+        let m = PhabMockServer::start().await;
+        let user = m.new_user("user", "Test User");
+        let t100 = m.new_simple_task(100, &user);
+        let t200 = m.new_simple_task(200, &user);
+        task::link(&t100, &t200);
+        task::link(&t200, &t100);
+
+        let client = Client::new(m.uri(), m.token().to_string());
+        let root = get_task(&client, 100).await;
+        let tree = client.task_tree(&[root]).query().await.unwrap();
+
+        assert_eq!(2, tree.len());
+        let cycles: Vec<_> = tree
+            .cycles()
+            .iter()
+            .map(|(from, to)| (from.id(), to.id()))
+            .collect();
+        assert_eq!(&[(200, 100)], cycles.as_slice());
+        assert_eq!("T100 Task T100\n  T200 Task T200\n", tree.to_string());
+    }
+}